@@ -41,7 +41,7 @@ export default function LendPage() {
     withdrawCollateral,
     isPoolInitialized,
     poolLoading,
-    initializePool
+    attestedActionsAvailable,
   } = useLending();
 
   const getMaxAmount = useCallback(() => {
@@ -83,22 +83,6 @@ export default function LendPage() {
         description: "Encrypting state and interacting with Solana...",
       });
 
-      // Initialize pool if not already initialized
-      if (!isPoolInitialized) {
-        toast.loading("Initializing lending pool...", {
-          id: loadingToast,
-          description: "Creating the lending pool on Solana...",
-        });
-        try {
-          await initializePool();
-          await refetchPool();
-        } catch (poolErr) {
-          // Pool might already exist or we don't have permission
-          console.warn("Pool initialization skipped or failed:", poolErr);
-          // Continue anyway - the pool might already exist
-        }
-      }
-
       let signature: string;
 
       switch (activeAction) {
@@ -189,12 +173,25 @@ export default function LendPage() {
             <div>
               <h4 className="text-sm font-semibold text-yellow-400 mb-1">Pool Not Initialized</h4>
               <p className="text-xs text-yellow-200/70">
-                The lending pool hasn&apos;t been created yet. Your first deposit will automatically initialize the pool on Solana.
+                The lending pool hasn&apos;t been created yet. Pools are created by the pool operator with their risk configuration and price feed.
               </p>
             </div>
           </motion.div>
         )}
 
+        {/* Attested actions are not wired up in this client yet */}
+        {!attestedActionsAvailable && (
+          <div className="mb-6 p-4 rounded-xl bg-yellow-500/10 border border-yellow-500/30 flex items-start gap-3">
+            <AlertTriangle className="w-5 h-5 text-yellow-500 flex-shrink-0 mt-0.5" />
+            <div>
+              <h4 className="text-sm font-semibold text-yellow-400 mb-1">Actions Unavailable</h4>
+              <p className="text-xs text-yellow-200/70">
+                Deposits, borrows, repayments and withdrawals need an Inco attestation that this client cannot request yet.
+              </p>
+            </div>
+          </div>
+        )}
+
         <div className="card shadow-lg border-[var(--color-border-accent)]">
           <div className="mb-8">
             <div className="flex items-center justify-between mb-2">
@@ -263,7 +260,7 @@ export default function LendPage() {
 
           <button
             onClick={handleSubmit}
-            disabled={loading || !amount || parseFloat(amount) <= 0}
+            disabled={!attestedActionsAvailable || loading || !amount || parseFloat(amount) <= 0}
             className="btn btn-primary w-full btn-lg relative overflow-hidden"
           >
             {loading ? (
@@ -3,6 +3,7 @@
 import { FC, useState, useEffect } from "react";
 import { useWallet } from "@solana/wallet-adapter-react";
 import { usePool, usePosition, useLending } from "@/hooks";
+import { ATTESTATION_UNAVAILABLE } from "@/hooks/useLending";
 import { formatSol, parseSolToLamports, bpsToPercent } from "@/lib/constants";
 import { deriveEncryptionKey, type EncryptionKeyPair } from "@/lib/encryption";
 import {
@@ -27,7 +28,16 @@ export const LendingForm: FC<LendingFormProps> = ({ initialAction = "deposit" })
   const { publicKey, signMessage } = useWallet();
   const { pool, stats } = usePool();
   const { position, decodedPosition, encryptionKey, initializeEncryption } = usePosition();
-  const { txState, depositCollateral, borrow, repay, withdrawCollateral, openPosition, resetTxState } = useLending();
+  const {
+    txState,
+    attestedActionsAvailable,
+    depositCollateral,
+    borrow,
+    repay,
+    withdrawCollateral,
+    openPosition,
+    resetTxState,
+  } = useLending();
 
   const [action, setAction] = useState<ActionType>(initialAction);
   const [amount, setAmount] = useState("");
@@ -244,6 +254,12 @@ export const LendingForm: FC<LendingFormProps> = ({ initialAction = "deposit" })
             </p>
           </div>
 
+          {!attestedActionsAvailable && (
+            <div className="p-3 rounded-lg bg-[var(--shadow-error-muted)] border border-[var(--shadow-error)] text-sm text-[var(--shadow-error)]">
+              {ATTESTATION_UNAVAILABLE}
+            </div>
+          )}
+
           {/* Error display */}
           {txState.status === "error" && (
             <div className="p-3 rounded-lg bg-[var(--shadow-error-muted)] border border-[var(--shadow-error)] text-sm text-[var(--shadow-error)]">
@@ -274,7 +290,7 @@ export const LendingForm: FC<LendingFormProps> = ({ initialAction = "deposit" })
             size="lg"
             onClick={handleAction}
             loading={txState.status === "pending" || txState.status === "confirming"}
-            disabled={!amount || parseFloat(amount) <= 0}
+            disabled={!attestedActionsAvailable || !amount || parseFloat(amount) <= 0}
           >
             {getButtonLabel()}
           </Button>
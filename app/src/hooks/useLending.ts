@@ -3,24 +3,13 @@
 import { useCallback, useState } from "react";
 import { useConnection, useWallet } from "@solana/wallet-adapter-react";
 import {
-  PublicKey,
   Transaction,
   TransactionInstruction,
   SystemProgram,
 } from "@solana/web3.js";
-import {
-  PROGRAM_ID,
-  getPoolPDA,
-  getPositionPDA,
-  getVaultPDA,
-  parseSolToLamports,
-} from "@/lib/constants";
-import {
-  encryptAmount,
-  generateBorrowProof,
-  generateWithdrawalProof,
-  type EncryptionKeyPair,
-} from "@/lib/encryption";
+import { PROGRAM_ID, getPoolPDA, getPositionPDA } from "@/lib/constants";
+import { instructionCoder } from "@/lib/program";
+import type { EncryptionKeyPair } from "@/lib/encryption";
 import type { TransactionState } from "@/types";
 import { usePool } from "./usePool";
 import { usePosition } from "./usePosition";
@@ -33,8 +22,13 @@ interface UseLendingResult {
   isPoolInitialized: boolean;
   poolLoading: boolean;
 
+  // Whether deposit, borrow, repay, withdraw and close can be submitted.
+  // Each needs an Inco attestation (an ed25519 signature from one of the
+  // pool's attesters over the proof digest) in the same transaction, which
+  // this client cannot obtain yet
+  attestedActionsAvailable: boolean;
+
   // Actions
-  initializePool: () => Promise<string>;
   openPosition: () => Promise<string>;
   depositCollateral: (
     amount: string,
@@ -58,22 +52,14 @@ interface UseLendingResult {
   resetTxState: () => void;
 }
 
-// Instruction discriminators (first 8 bytes of sha256("global:<instruction_name>"))
-const DISCRIMINATORS = {
-  initializePool: Buffer.from([0x5f, 0x64, 0x0e, 0x68, 0x17, 0x4b, 0x3f, 0x6c]),
-  openPosition: Buffer.from([135, 128, 47, 77, 15, 152, 240, 49]),
-  depositCollateral: Buffer.from([138, 147, 160, 52, 220, 190, 117, 13]),
-  borrow: Buffer.from([228, 253, 131, 202, 207, 116, 166, 78]),
-  repay: Buffer.from([234, 103, 67, 82, 247, 168, 156, 32]),
-  withdrawCollateral: Buffer.from([115, 135, 168, 106, 139, 214, 138, 150]),
-  closePosition: Buffer.from([123, 134, 81, 0, 49, 68, 98, 98]),
-};
+export const ATTESTATION_UNAVAILABLE =
+  "This action needs an Inco attestation, which this client cannot request yet";
 
 export function useLending(): UseLendingResult {
   const { connection } = useConnection();
   const { publicKey, sendTransaction } = useWallet();
   const { pool, loading: poolLoading, refetch: refetchPool } = usePool();
-  const { position, decodedPosition, refetch: refetchPosition } = usePosition();
+  const { refetch: refetchPosition } = usePosition();
 
   const [txState, setTxState] = useState<TransactionState>({
     type: "deposit",
@@ -128,291 +114,62 @@ export function useLending(): UseLendingResult {
     [connection, publicKey, sendTransaction, refetchPool, refetchPosition]
   );
 
-  // Initialize pool - creates the lending pool on-chain if it doesn't exist
-  const initializePool = useCallback(async (): Promise<string> => {
-    if (!publicKey) throw new Error("Wallet not connected");
-
-    const [poolPDA] = getPoolPDA();
-    const [vaultPDA] = getVaultPDA(poolPDA);
-
-    // Default pool parameters
-    const ltvRatio = 7500; // 75%
-    const interestRate = 500; // 5%
-    const liquidationThreshold = 8000; // 80%
-
-    // Build instruction data: discriminator + ltv_ratio (u16) + interest_rate (u16) + liquidation_threshold (u16)
-    const data = Buffer.alloc(8 + 2 + 2 + 2);
-    DISCRIMINATORS.initializePool.copy(data, 0);
-    data.writeUInt16LE(ltvRatio, 8);
-    data.writeUInt16LE(interestRate, 10);
-    data.writeUInt16LE(liquidationThreshold, 12);
-
-    const instruction = new TransactionInstruction({
-      keys: [
-        { pubkey: poolPDA, isSigner: false, isWritable: true },
-        { pubkey: vaultPDA, isSigner: false, isWritable: true },
-        { pubkey: publicKey, isSigner: true, isWritable: true },
-        { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
-      ],
-      programId: PROGRAM_ID,
-      data,
-    });
-
-    return executeTransaction(instruction, "deposit");
-  }, [publicKey, executeTransaction]);
-
   const openPosition = useCallback(async (): Promise<string> => {
     if (!publicKey) throw new Error("Wallet not connected");
 
     const [poolPDA] = getPoolPDA();
     const [positionPDA] = getPositionPDA(publicKey);
 
+    // Accounts and data follow `openPosition` in public/idl/shadow_lend.json
     const instruction = new TransactionInstruction({
       keys: [
-        { pubkey: poolPDA, isSigner: false, isWritable: false },
         { pubkey: poolPDA, isSigner: false, isWritable: true },
         { pubkey: positionPDA, isSigner: false, isWritable: true },
         { pubkey: publicKey, isSigner: true, isWritable: true },
         { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
       ],
       programId: PROGRAM_ID,
-      data: DISCRIMINATORS.openPosition,
+      data: instructionCoder.encode("openPosition", { positionIndex: 0 }),
     });
 
     return executeTransaction(instruction, "deposit");
   }, [publicKey, executeTransaction]);
 
-  const depositCollateral = useCallback(
-    async (
-      amountSol: string,
-      encryptionKey: EncryptionKeyPair
-    ): Promise<string> => {
-      if (!publicKey || !pool) throw new Error("Wallet not connected or pool not loaded");
-
-      const amount = parseSolToLamports(amountSol);
-      if (amount <= 0) throw new Error("Invalid amount");
-
-      const [poolPDA] = getPoolPDA();
-      const [positionPDA] = getPositionPDA(publicKey);
-      const [vaultPDA] = getVaultPDA(poolPDA);
-
-      // Encrypt the amount
-      const encryptedAmount = encryptAmount(BigInt(amount), encryptionKey);
-
-      // Build instruction data: discriminator + amount (u64) + encrypted_amount ([u8; 32])
-      const data = Buffer.alloc(8 + 8 + 32);
-      DISCRIMINATORS.depositCollateral.copy(data, 0);
-      data.writeBigUInt64LE(BigInt(amount), 8);
-      Buffer.from(encryptedAmount).copy(data, 16);
-
-      const instruction = new TransactionInstruction({
-        keys: [
-          { pubkey: poolPDA, isSigner: false, isWritable: true },
-          { pubkey: positionPDA, isSigner: false, isWritable: true },
-          { pubkey: vaultPDA, isSigner: false, isWritable: true },
-          { pubkey: publicKey, isSigner: true, isWritable: true },
-          { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
-        ],
-        programId: PROGRAM_ID,
-        data,
-      });
-
-      return executeTransaction(instruction, "deposit");
+  // Rejects an action that needs an attestation, surfacing why in txState
+  const attestationRequired = useCallback(
+    async (type: TransactionState["type"]): Promise<string> => {
+      setTxState({ type, status: "error", error: ATTESTATION_UNAVAILABLE });
+      throw new Error(ATTESTATION_UNAVAILABLE);
     },
-    [publicKey, pool, executeTransaction]
+    []
   );
 
+  const depositCollateral = useCallback(
+    () => attestationRequired("deposit"),
+    [attestationRequired]
+  );
   const borrow = useCallback(
-    async (
-      amountSol: string,
-      encryptionKey: EncryptionKeyPair
-    ): Promise<string> => {
-      if (!publicKey || !pool || !position) {
-        throw new Error("Wallet not connected or position not loaded");
-      }
-
-      const amount = parseSolToLamports(amountSol);
-      if (amount <= 0) throw new Error("Invalid amount");
-
-      const [poolPDA] = getPoolPDA();
-      const [positionPDA] = getPositionPDA(publicKey);
-      const [vaultPDA] = getVaultPDA(poolPDA);
-
-      // Calculate new debt (existing + new borrow)
-      const currentDebt = decodedPosition?.debt ?? BigInt(0);
-      const newDebt = currentDebt + BigInt(amount);
-      const encryptedNewDebt = encryptAmount(newDebt, encryptionKey);
-
-      // Generate Inco proof
-      const proof = await generateBorrowProof(
-        position.encryptedCollateral,
-        position.encryptedDebt,
-        BigInt(amount),
-        pool.ltvRatio
-      );
-
-      // Build instruction data: discriminator + amount + encrypted_new_debt + proof_len + proof
-      const data = Buffer.alloc(8 + 8 + 32 + 4 + proof.length);
-      let offset = 0;
-      DISCRIMINATORS.borrow.copy(data, offset);
-      offset += 8;
-      data.writeBigUInt64LE(BigInt(amount), offset);
-      offset += 8;
-      Buffer.from(encryptedNewDebt).copy(data, offset);
-      offset += 32;
-      data.writeUInt32LE(proof.length, offset);
-      offset += 4;
-      Buffer.from(proof).copy(data, offset);
-
-      const instruction = new TransactionInstruction({
-        keys: [
-          { pubkey: poolPDA, isSigner: false, isWritable: false },
-          { pubkey: poolPDA, isSigner: false, isWritable: true },
-          { pubkey: positionPDA, isSigner: false, isWritable: true },
-          { pubkey: vaultPDA, isSigner: false, isWritable: true },
-          { pubkey: publicKey, isSigner: true, isWritable: true },
-          { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
-        ],
-        programId: PROGRAM_ID,
-        data,
-      });
-
-      return executeTransaction(instruction, "borrow");
-    },
-    [publicKey, pool, position, decodedPosition, executeTransaction]
+    () => attestationRequired("borrow"),
+    [attestationRequired]
   );
-
   const repay = useCallback(
-    async (
-      amountSol: string,
-      encryptionKey: EncryptionKeyPair
-    ): Promise<string> => {
-      if (!publicKey || !pool || !decodedPosition) {
-        throw new Error("Wallet not connected or position not loaded");
-      }
-
-      const amount = parseSolToLamports(amountSol);
-      if (amount <= 0) throw new Error("Invalid amount");
-
-      const [poolPDA] = getPoolPDA();
-      const [positionPDA] = getPositionPDA(publicKey);
-      const [vaultPDA] = getVaultPDA(poolPDA);
-
-      // Calculate new debt after repayment
-      const newDebt =
-        decodedPosition.debt > BigInt(amount)
-          ? decodedPosition.debt - BigInt(amount)
-          : BigInt(0);
-      const encryptedNewDebt = encryptAmount(newDebt, encryptionKey);
-
-      // Build instruction data
-      const data = Buffer.alloc(8 + 8 + 32);
-      DISCRIMINATORS.repay.copy(data, 0);
-      data.writeBigUInt64LE(BigInt(amount), 8);
-      Buffer.from(encryptedNewDebt).copy(data, 16);
-
-      const instruction = new TransactionInstruction({
-        keys: [
-          { pubkey: poolPDA, isSigner: false, isWritable: true },
-          { pubkey: positionPDA, isSigner: false, isWritable: true },
-          { pubkey: vaultPDA, isSigner: false, isWritable: true },
-          { pubkey: publicKey, isSigner: true, isWritable: true },
-          { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
-        ],
-        programId: PROGRAM_ID,
-        data,
-      });
-
-      return executeTransaction(instruction, "repay");
-    },
-    [publicKey, pool, decodedPosition, executeTransaction]
+    () => attestationRequired("repay"),
+    [attestationRequired]
   );
-
   const withdrawCollateral = useCallback(
-    async (
-      amountSol: string,
-      encryptionKey: EncryptionKeyPair
-    ): Promise<string> => {
-      if (!publicKey || !pool || !position || !decodedPosition) {
-        throw new Error("Wallet not connected or position not loaded");
-      }
-
-      const amount = parseSolToLamports(amountSol);
-      if (amount <= 0) throw new Error("Invalid amount");
-
-      const [poolPDA] = getPoolPDA();
-      const [positionPDA] = getPositionPDA(publicKey);
-      const [vaultPDA] = getVaultPDA(poolPDA);
-
-      // Calculate new collateral after withdrawal
-      const newCollateral =
-        decodedPosition.collateral > BigInt(amount)
-          ? decodedPosition.collateral - BigInt(amount)
-          : BigInt(0);
-      const encryptedNewCollateral = encryptAmount(newCollateral, encryptionKey);
-
-      // Generate withdrawal proof
-      const proof = await generateWithdrawalProof(
-        position.encryptedCollateral,
-        position.encryptedDebt,
-        BigInt(amount),
-        pool.ltvRatio
-      );
-
-      // Build instruction data
-      const data = Buffer.alloc(8 + 8 + 32 + 4 + proof.length);
-      let offset = 0;
-      DISCRIMINATORS.withdrawCollateral.copy(data, offset);
-      offset += 8;
-      data.writeBigUInt64LE(BigInt(amount), offset);
-      offset += 8;
-      Buffer.from(encryptedNewCollateral).copy(data, offset);
-      offset += 32;
-      data.writeUInt32LE(proof.length, offset);
-      offset += 4;
-      Buffer.from(proof).copy(data, offset);
-
-      const instruction = new TransactionInstruction({
-        keys: [
-          { pubkey: poolPDA, isSigner: false, isWritable: true },
-          { pubkey: positionPDA, isSigner: false, isWritable: true },
-          { pubkey: vaultPDA, isSigner: false, isWritable: true },
-          { pubkey: publicKey, isSigner: true, isWritable: true },
-          { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
-        ],
-        programId: PROGRAM_ID,
-        data,
-      });
-
-      return executeTransaction(instruction, "withdraw");
-    },
-    [publicKey, pool, position, decodedPosition, executeTransaction]
+    () => attestationRequired("withdraw"),
+    [attestationRequired]
+  );
+  const closePosition = useCallback(
+    () => attestationRequired("withdraw"),
+    [attestationRequired]
   );
-
-  const closePosition = useCallback(async (): Promise<string> => {
-    if (!publicKey) throw new Error("Wallet not connected");
-
-    const [poolPDA] = getPoolPDA();
-    const [positionPDA] = getPositionPDA(publicKey);
-
-    const instruction = new TransactionInstruction({
-      keys: [
-        { pubkey: poolPDA, isSigner: false, isWritable: true },
-        { pubkey: positionPDA, isSigner: false, isWritable: true },
-        { pubkey: publicKey, isSigner: true, isWritable: true },
-      ],
-      programId: PROGRAM_ID,
-      data: DISCRIMINATORS.closePosition,
-    });
-
-    return executeTransaction(instruction, "withdraw");
-  }, [publicKey, executeTransaction]);
 
   return {
     txState,
     isPoolInitialized: pool !== null,
     poolLoading,
-    initializePool,
+    attestedActionsAvailable: false,
     openPosition,
     depositCollateral,
     borrow,
@@ -2,8 +2,8 @@
 
 import { useCallback, useEffect, useState } from "react";
 import { useConnection } from "@solana/wallet-adapter-react";
-import { PublicKey } from "@solana/web3.js";
-import { getPoolPDA, PROGRAM_ID, bpsToPercent } from "@/lib/constants";
+import { getPoolPDA, bpsToPercent } from "@/lib/constants";
+import { decodePool } from "@/lib/program";
 import type { Pool, ProtocolStats } from "@/types";
 
 interface UsePoolResult {
@@ -14,59 +14,6 @@ interface UsePoolResult {
   refetch: () => Promise<void>;
 }
 
-// Pool account data layout (matches Anchor serialization)
-const POOL_SIZE = 8 + 32 + 32 + 32 + 2 + 2 + 2 + 8 + 8 + 8 + 1 + 1; // discriminator + fields
-
-function deserializePool(data: Buffer): Pool {
-  let offset = 8; // Skip discriminator
-
-  const authority = new PublicKey(data.subarray(offset, offset + 32));
-  offset += 32;
-
-  const collateralMint = new PublicKey(data.subarray(offset, offset + 32));
-  offset += 32;
-
-  const borrowMint = new PublicKey(data.subarray(offset, offset + 32));
-  offset += 32;
-
-  const ltvRatio = data.readUInt16LE(offset);
-  offset += 2;
-
-  const interestRate = data.readUInt16LE(offset);
-  offset += 2;
-
-  const liquidationThreshold = data.readUInt16LE(offset);
-  offset += 2;
-
-  const totalDeposits = data.readBigUInt64LE(offset);
-  offset += 8;
-
-  const totalBorrows = data.readBigUInt64LE(offset);
-  offset += 8;
-
-  const activePositions = data.readBigUInt64LE(offset);
-  offset += 8;
-
-  const bump = data.readUInt8(offset);
-  offset += 1;
-
-  const isActive = data.readUInt8(offset) === 1;
-
-  return {
-    authority,
-    collateralMint,
-    borrowMint,
-    ltvRatio,
-    interestRate,
-    liquidationThreshold,
-    totalDeposits,
-    totalBorrows,
-    activePositions,
-    bump,
-    isActive,
-  };
-}
-
 export function usePool(): UsePoolResult {
   const { connection } = useConnection();
   const [pool, setPool] = useState<Pool | null>(null);
@@ -89,7 +36,7 @@ export function usePool(): UsePoolResult {
         return;
       }
 
-      const poolData = deserializePool(Buffer.from(accountInfo.data));
+      const poolData = decodePool(Buffer.from(accountInfo.data));
       setPool(poolData);
 
       // Derive public stats from pool
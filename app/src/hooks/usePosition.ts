@@ -2,8 +2,8 @@
 
 import { useCallback, useEffect, useState } from "react";
 import { useConnection, useWallet } from "@solana/wallet-adapter-react";
-import { PublicKey } from "@solana/web3.js";
-import { getPositionPDA, getPoolPDA, bpsToPercent } from "@/lib/constants";
+import { getPositionPDA } from "@/lib/constants";
+import { decodePosition } from "@/lib/program";
 import {
   deriveEncryptionKey,
   decryptAmount,
@@ -23,43 +23,6 @@ interface UsePositionResult {
   initializeEncryption: () => Promise<EncryptionKeyPair>;
 }
 
-// Position account data layout
-const POSITION_SIZE = 8 + 32 + 32 + 32 + 32 + 8 + 1 + 1; // discriminator + fields
-
-function deserializePosition(data: Buffer): Position {
-  let offset = 8; // Skip discriminator
-
-  const owner = new PublicKey(data.subarray(offset, offset + 32));
-  offset += 32;
-
-  const pool = new PublicKey(data.subarray(offset, offset + 32));
-  offset += 32;
-
-  const encryptedCollateral = new Uint8Array(data.subarray(offset, offset + 32));
-  offset += 32;
-
-  const encryptedDebt = new Uint8Array(data.subarray(offset, offset + 32));
-  offset += 32;
-
-  const lastUpdate = data.readBigInt64LE(offset);
-  offset += 8;
-
-  const isActive = data.readUInt8(offset) === 1;
-  offset += 1;
-
-  const bump = data.readUInt8(offset);
-
-  return {
-    owner,
-    pool,
-    encryptedCollateral,
-    encryptedDebt,
-    lastUpdate,
-    isActive,
-    bump,
-  };
-}
-
 export function usePosition(): UsePositionResult {
   const { connection } = useConnection();
   const { publicKey, signMessage } = useWallet();
@@ -111,7 +74,7 @@ export function usePosition(): UsePositionResult {
         return;
       }
 
-      const positionData = deserializePosition(Buffer.from(accountInfo.data));
+      const positionData = decodePosition(Buffer.from(accountInfo.data));
       setPosition(positionData);
 
       // Decrypt position data if we have the encryption key
@@ -3,7 +3,13 @@
 import { useEffect, useState, useCallback } from "react";
 import { useConnection, useWallet } from "@solana/wallet-adapter-react";
 import { PublicKey, LAMPORTS_PER_SOL } from "@solana/web3.js";
-import { getPoolPDA, getPositionPDA, PROGRAM_ID } from "@/lib/program";
+import {
+    getPoolPDA,
+    getPositionPDA,
+    decodePool,
+    decodePosition,
+    PROGRAM_ID,
+} from "@/lib/program";
 import {
     decryptAmount,
     deriveEncryptionKey,
@@ -19,7 +25,7 @@ export interface PoolState {
     ltvRatio: number;
     interestRate: number;
     liquidationThreshold: number;
-    totalDeposits: number; // In lamports
+    totalDeposits: number; // Borrow-asset base units
     totalBorrows: number;
     activePositions: number;
     isActive: boolean;
@@ -45,7 +51,7 @@ export interface DecryptedPosition {
 
 // Pool stats for public display
 export interface PoolStats {
-    tvl: number; // Total SOL locked
+    tvl: number; // Total borrow-asset tokens supplied
     totalBorrowed: number;
     activePositions: number;
     ltvRatio: number;
@@ -98,67 +104,40 @@ export function usePool() {
                 return;
             }
 
-            // Deserialize pool data
-            const data = accountInfo.data;
-
-            // Skip 8-byte discriminator
-            let offset = 8;
-
-            const authority = new PublicKey(data.slice(offset, offset + 32));
-            offset += 32;
-
-            const collateralMint = new PublicKey(data.slice(offset, offset + 32));
-            offset += 32;
-
-            const borrowMint = new PublicKey(data.slice(offset, offset + 32));
-            offset += 32;
-
-            const ltvRatio = data.readUInt16LE(offset);
-            offset += 2;
-
-            const interestRate = data.readUInt16LE(offset);
-            offset += 2;
-
-            const liquidationThreshold = data.readUInt16LE(offset);
-            offset += 2;
-
-            const totalDeposits = Number(data.readBigUInt64LE(offset));
-            offset += 8;
-
-            const totalBorrows = Number(data.readBigUInt64LE(offset));
-            offset += 8;
-
-            const activePositions = Number(data.readBigUInt64LE(offset));
-            offset += 8;
+            const decoded = decodePool(Buffer.from(accountInfo.data));
+            const totalDeposits = Number(decoded.totalDeposits);
+            const totalBorrows = Number(decoded.totalBorrows);
+            const activePositions = Number(decoded.activePositions);
 
             const poolState: PoolState = {
-                authority,
-                collateralMint,
-                borrowMint,
-                ltvRatio,
-                interestRate,
-                liquidationThreshold,
+                authority: decoded.authority,
+                collateralMint: decoded.collateralMint,
+                borrowMint: decoded.borrowMint,
+                ltvRatio: decoded.ltvRatio,
+                interestRate: decoded.interestRate,
+                liquidationThreshold: decoded.liquidationThreshold,
                 totalDeposits,
                 totalBorrows,
                 activePositions,
-                isActive: data[offset + 1] === 1,
+                isActive: decoded.isActive,
             };
 
             setPool(poolState);
 
-            // Calculate public stats
-            const tvlSol = totalDeposits / LAMPORTS_PER_SOL;
-            const borrowedSol = totalBorrows / LAMPORTS_PER_SOL;
+            // Calculate public stats in whole borrow-asset tokens
+            const unit = 10 ** decoded.borrowDecimals;
+            const tvl = totalDeposits / unit;
+            const borrowed = totalBorrows / unit;
             const utilization = totalDeposits > 0
                 ? (totalBorrows / totalDeposits) * 100
                 : 0;
 
             setStats({
-                tvl: tvlSol,
-                totalBorrowed: borrowedSol,
+                tvl,
+                totalBorrowed: borrowed,
                 activePositions,
-                ltvRatio: ltvRatio / 100,
-                interestRate: interestRate / 100,
+                ltvRatio: decoded.ltvRatio / 100,
+                interestRate: decoded.interestRate / 100,
                 utilizationRate: utilization,
             });
 
@@ -240,37 +219,15 @@ export function usePosition() {
                 return;
             }
 
-            // Deserialize position data
-            const data = accountInfo.data;
-            let offset = 8; // Skip discriminator
-
-            const owner = new PublicKey(data.slice(offset, offset + 32));
-            offset += 32;
-
-            const pool = new PublicKey(data.slice(offset, offset + 32));
-            offset += 32;
-
-            // Skip length prefix for encrypted data (4 bytes each)
-            offset += 4;
-            const encryptedCollateral = data.slice(offset, offset + 32);
-            offset += 32;
-
-            offset += 4;
-            const encryptedDebt = data.slice(offset, offset + 32);
-            offset += 32;
-
-            const lastUpdate = Number(data.readBigInt64LE(offset));
-            offset += 8;
-
-            const isActive = data[offset] === 1;
+            const decoded = decodePosition(Buffer.from(accountInfo.data));
 
             const positionState: PositionState = {
-                owner,
-                pool,
-                encryptedCollateral: new Uint8Array(encryptedCollateral),
-                encryptedDebt: new Uint8Array(encryptedDebt),
-                lastUpdate,
-                isActive,
+                owner: decoded.owner,
+                pool: decoded.pool,
+                encryptedCollateral: decoded.encryptedCollateral,
+                encryptedDebt: decoded.encryptedDebt,
+                lastUpdate: Number(decoded.lastUpdate),
+                isActive: decoded.isActive,
             };
 
             setPosition(positionState);
@@ -6,36 +6,85 @@ export const PROGRAM_ID = new PublicKey(
     "8dBNWFxxdvHmoZWKuS1rGzGGmBxdXxHXauYAiTPM4Zan"
 );
 
-// Pool seed
+// PDA seeds (must match programs/shadow-lend/src/lib.rs)
 export const POOL_SEED = Buffer.from("pool");
-
-// Position seed prefix
 export const POSITION_SEED = Buffer.from("position");
+export const COLLATERAL_VAULT_SEED = Buffer.from("collateral_vault");
+export const LIQUIDITY_VAULT_SEED = Buffer.from("liquidity_vault");
+export const INSURANCE_VAULT_SEED = Buffer.from("insurance_vault");
+export const SHARE_MINT_SEED = Buffer.from("share_mint");
+export const CONFIG_CHANGE_SEED = Buffer.from("config_change");
 
-// Vault seed prefix
-export const VAULT_SEED = Buffer.from("vault");
+// Pool the app talks to, keyed by asset pair and pool id
+export const COLLATERAL_MINT = new PublicKey(
+  process.env.NEXT_PUBLIC_COLLATERAL_MINT ||
+    "So11111111111111111111111111111111111111112"
+);
+export const BORROW_MINT = new PublicKey(
+  process.env.NEXT_PUBLIC_BORROW_MINT ||
+    "EPjFWJd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"
+);
+export const POOL_ID = Number(process.env.NEXT_PUBLIC_POOL_ID || 0);
 
-// Derive pool PDA
-export function getPoolPDA(): [PublicKey, number] {
-  return PublicKey.findProgramAddressSync([POOL_SEED], PROGRAM_ID);
+function u32LE(value: number): Buffer {
+  const buf = Buffer.alloc(4);
+  buf.writeUInt32LE(value, 0);
+  return buf;
 }
 
-// Derive position PDA for a user
-export function getPositionPDA(owner: PublicKey): [PublicKey, number] {
+// Derive pool PDA: ["pool", collateral_mint, borrow_mint, pool_id (u32 LE)]
+export function getPoolPDA(
+  collateralMint: PublicKey = COLLATERAL_MINT,
+  borrowMint: PublicKey = BORROW_MINT,
+  poolId: number = POOL_ID
+): [PublicKey, number] {
   return PublicKey.findProgramAddressSync(
-    [POSITION_SEED, owner.toBuffer()],
+    [POOL_SEED, collateralMint.toBuffer(), borrowMint.toBuffer(), u32LE(poolId)],
     PROGRAM_ID
   );
 }
 
-// Derive vault PDA
-export function getVaultPDA(pool: PublicKey): [PublicKey, number] {
+// Derive position PDA: ["position", pool, owner, index (u32 LE)]
+export function getPositionPDA(
+  owner: PublicKey,
+  index = 0,
+  pool: PublicKey = getPoolPDA()[0]
+): [PublicKey, number] {
   return PublicKey.findProgramAddressSync(
-    [VAULT_SEED, pool.toBuffer()],
+    [POSITION_SEED, pool.toBuffer(), owner.toBuffer(), u32LE(index)],
     PROGRAM_ID
   );
 }
 
+function poolSeededPDA(seed: Buffer, pool: PublicKey): [PublicKey, number] {
+  return PublicKey.findProgramAddressSync([seed, pool.toBuffer()], PROGRAM_ID);
+}
+
+// Collateral token vault
+export function getCollateralVaultPDA(pool: PublicKey): [PublicKey, number] {
+  return poolSeededPDA(COLLATERAL_VAULT_SEED, pool);
+}
+
+// Lender liquidity (borrow token) vault
+export function getLiquidityVaultPDA(pool: PublicKey): [PublicKey, number] {
+  return poolSeededPDA(LIQUIDITY_VAULT_SEED, pool);
+}
+
+// Insurance fund (borrow token) vault
+export function getInsuranceVaultPDA(pool: PublicKey): [PublicKey, number] {
+  return poolSeededPDA(INSURANCE_VAULT_SEED, pool);
+}
+
+// Lender share token mint
+export function getShareMintPDA(pool: PublicKey): [PublicKey, number] {
+  return poolSeededPDA(SHARE_MINT_SEED, pool);
+}
+
+// Timelocked config change queued for a pool
+export function getConfigChangePDA(pool: PublicKey): [PublicKey, number] {
+  return poolSeededPDA(CONFIG_CHANGE_SEED, pool);
+}
+
 // Lamports per SOL
 export const LAMPORTS_PER_SOL = 1_000_000_000;
 
@@ -1,70 +1,145 @@
+import {
+    BorshAccountsCoder,
+    BorshInstructionCoder,
+    type BN,
+    type Idl,
+} from "@coral-xyz/anchor";
 import { PublicKey } from "@solana/web3.js";
+import idl from "../../public/idl/shadow_lend.json";
+import type { Pool, Position } from "@/types";
 
-// Program ID - Update after deployment
-export const PROGRAM_ID = new PublicKey(
-    "8dBNWFxxdvHmoZWKuS1rGzGGmBxdXxHXauYAiTPM4Zan"
-);
+// Program ID and PDA helpers live in constants so there is one set of seeds
+import { getPoolPDA as derivePool, getPositionPDA as derivePosition } from "./constants";
 
-// PDAs
-export const getPoolPDA = () => {
-    const [pda] = PublicKey.findProgramAddressSync(
-        [Buffer.from("pool")],
-        PROGRAM_ID
-    );
-    return pda;
-};
+export {
+    PROGRAM_ID,
+    getCollateralVaultPDA,
+    getLiquidityVaultPDA,
+    getInsuranceVaultPDA,
+    getShareMintPDA,
+    getConfigChangePDA,
+} from "./constants";
 
-export const getPositionPDA = (owner: PublicKey) => {
-    const [pda] = PublicKey.findProgramAddressSync(
-        [Buffer.from("position"), owner.toBuffer()],
-        PROGRAM_ID
-    );
-    return pda;
-};
+export const getPoolPDA = () => derivePool()[0];
+
+export const getPositionPDA = (owner: PublicKey, index = 0) =>
+    derivePosition(owner, index)[0];
+
+// Generated from the program (see DEPLOYMENT.md); account layouts and
+// instruction discriminators come from here rather than hand-written offsets
+export const IDL = idl as unknown as Idl;
+
+const accountsCoder = new BorshAccountsCoder(IDL);
+export const instructionCoder = new BorshInstructionCoder(IDL);
+
+const BPS = 10_000;
+
+const toBigInt = (value: BN) => BigInt(value.toString());
+
+interface RawRateModel {
+    baseRate: number;
+    slopeLow: number;
+    slopeHigh: number;
+    optimalUtilization: number;
+}
 
-export const getVaultPDA = (pool: PublicKey) => {
-    const [pda] = PublicKey.findProgramAddressSync(
-        [Buffer.from("vault"), pool.toBuffer()],
-        PROGRAM_ID
+// Fields of the on-chain accounts the app reads, as decoded by the IDL coder
+interface RawPool {
+    authority: PublicKey;
+    collateralMint: PublicKey;
+    borrowMint: PublicKey;
+    poolId: number;
+    shareMint: PublicKey;
+    ltvRatio: number;
+    rateModel: RawRateModel;
+    liquidationThreshold: number;
+    collateralDecimals: number;
+    borrowDecimals: number;
+    totalCollateral: BN;
+    totalDeposits: BN;
+    totalBorrows: BN;
+    activePositions: BN;
+    pauseFlags: number;
+    bump: number;
+}
+
+interface RawPosition {
+    owner: PublicKey;
+    pool: PublicKey;
+    index: number;
+    encryptedCollateral: number[];
+    encryptedDebt: number[];
+    debtIndex: BN;
+    nonce: BN;
+    lastUpdate: BN;
+    isActive: boolean;
+    bump: number;
+}
+
+// Utilization in basis points, capped at 100% (Pool::utilization)
+export function utilizationBps(totalDeposits: bigint, totalBorrows: bigint): number {
+    if (totalDeposits === BigInt(0)) return 0;
+    const utilization = Number((totalBorrows * BigInt(BPS)) / totalDeposits);
+    return Math.min(utilization, BPS);
+}
+
+// Annual borrow rate in basis points at `utilization` (InterestRateModel::borrow_rate)
+export function borrowRateBps(model: RawRateModel, utilization: number): number {
+    const u = Math.min(utilization, BPS);
+    const optimal = model.optimalUtilization;
+    if (u <= optimal) {
+        if (optimal === 0) return model.baseRate;
+        return model.baseRate + Math.floor((model.slopeLow * u) / optimal);
+    }
+    const excess = u - optimal;
+    return (
+        model.baseRate +
+        model.slopeLow +
+        Math.floor((model.slopeHigh * excess) / (BPS - optimal))
     );
-    return pda;
-};
-
-// Account Sizes (for rent calculation)
-export const POOL_SIZE = 8 + 32 + 32 + 32 + 2 + 2 + 2 + 8 + 8 + 8 + 1 + 1;
-export const POSITION_SIZE = 8 + 32 + 32 + 32 + 32 + 8 + 1 + 1;
-
-// Instruction discriminators (first 8 bytes of sha256 hash of instruction name)
-export const INSTRUCTIONS = {
-    initializePool: Buffer.from([
-        // anchor discriminator for "initialize_pool"
-        0x5f, 0x64, 0x0e, 0x68, 0x17, 0x4b, 0x3f, 0x6c,
-    ]),
-    openPosition: Buffer.from([
-        0x87, 0x80, 0x22, 0x50, 0x66, 0x2c, 0xfe, 0xef,
-    ]),
-    depositCollateral: Buffer.from([
-        0x2c, 0x01, 0x6a, 0x40, 0x7b, 0x5c, 0x4e, 0x90,
-    ]),
-    borrow: Buffer.from([
-        0x22, 0x8d, 0x2e, 0x2f, 0x7a, 0x9e, 0x8b, 0x34,
-    ]),
-    repay: Buffer.from([
-        0xd8, 0x5b, 0x0e, 0x98, 0x8c, 0x2d, 0x6f, 0x1a,
-    ]),
-    withdrawCollateral: Buffer.from([
-        0xb7, 0x12, 0x46, 0x95, 0x61, 0xa4, 0x0a, 0xce,
-    ]),
-    liquidate: Buffer.from([
-        0xdf, 0x04, 0x70, 0x52, 0x86, 0xb4, 0x1a, 0x7e,
-    ]),
-    closePosition: Buffer.from([
-        0x7b, 0x86, 0x51, 0x0a, 0x07, 0x44, 0x1c, 0xb4,
-    ]),
-};
-
-// Interest rate model constants
-export const INTEREST_RATE_BASE = 300; // 3% base rate in basis points
-export const INTEREST_RATE_SLOPE = 200; // 2% slope
-export const LTV_RATIO = 7500; // 75%
-export const LIQUIDATION_THRESHOLD = 8000; // 80%
+}
+
+export function decodePool(data: Buffer): Pool {
+    const raw = accountsCoder.decode<RawPool>("Pool", data);
+    const totalDeposits = toBigInt(raw.totalDeposits);
+    const totalBorrows = toBigInt(raw.totalBorrows);
+    const rateModel = raw.rateModel;
+
+    return {
+        authority: raw.authority,
+        collateralMint: raw.collateralMint,
+        borrowMint: raw.borrowMint,
+        poolId: raw.poolId,
+        shareMint: raw.shareMint,
+        ltvRatio: raw.ltvRatio,
+        liquidationThreshold: raw.liquidationThreshold,
+        rateModel,
+        interestRate: borrowRateBps(rateModel, utilizationBps(totalDeposits, totalBorrows)),
+        collateralDecimals: raw.collateralDecimals,
+        borrowDecimals: raw.borrowDecimals,
+        totalCollateral: toBigInt(raw.totalCollateral),
+        totalDeposits,
+        totalBorrows,
+        activePositions: toBigInt(raw.activePositions),
+        pauseFlags: raw.pauseFlags,
+        bump: raw.bump,
+        isActive: raw.pauseFlags === 0,
+    };
+}
+
+export function decodePosition(data: Buffer): Position {
+    const raw = accountsCoder.decode<RawPosition>("Position", data);
+
+    return {
+        owner: raw.owner,
+        pool: raw.pool,
+        index: raw.index,
+        encryptedCollateral: Uint8Array.from(raw.encryptedCollateral),
+        encryptedDebt: Uint8Array.from(raw.encryptedDebt),
+        debtIndex: toBigInt(raw.debtIndex),
+        nonce: toBigInt(raw.nonce),
+        lastUpdate: toBigInt(raw.lastUpdate),
+        isActive: raw.isActive,
+        bump: raw.bump,
+    };
+}
@@ -5,25 +5,42 @@
 
 import { PublicKey } from "@solana/web3.js";
 
+export interface InterestRateModel {
+  baseRate: number; // basis points per year
+  slopeLow: number; // added from 0 to optimal utilization
+  slopeHigh: number; // added from optimal to 100% utilization
+  optimalUtilization: number; // basis points
+}
+
 export interface Pool {
   authority: PublicKey;
   collateralMint: PublicKey;
   borrowMint: PublicKey;
+  poolId: number;
+  shareMint: PublicKey;
   ltvRatio: number; // basis points
-  interestRate: number; // basis points
   liquidationThreshold: number; // basis points
-  totalDeposits: bigint;
-  totalBorrows: bigint;
+  rateModel: InterestRateModel;
+  interestRate: number; // current borrow rate in basis points, from rateModel
+  collateralDecimals: number;
+  borrowDecimals: number;
+  totalCollateral: bigint; // collateral base units
+  totalDeposits: bigint; // borrow-asset base units
+  totalBorrows: bigint; // borrow-asset base units
   activePositions: bigint;
+  pauseFlags: number;
   bump: number;
-  isActive: boolean;
+  isActive: boolean; // no operation is paused
 }
 
 export interface Position {
   owner: PublicKey;
   pool: PublicKey;
+  index: number;
   encryptedCollateral: Uint8Array; // 32 bytes
   encryptedDebt: Uint8Array; // 32 bytes
+  debtIndex: bigint; // pool borrow index the debt was last written at
+  nonce: bigint; // consumed by each attested transition
   lastUpdate: bigint;
   isActive: boolean;
   bump: number;
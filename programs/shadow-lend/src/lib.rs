@@ -1,4 +1,8 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::ed25519_program;
+use anchor_lang::solana_program::hash::hashv;
+use anchor_lang::solana_program::sysvar::instructions as sysvar_instructions;
+use anchor_spl::token::{self, Burn, Mint, MintTo, Token, TokenAccount, Transfer};
 
 declare_id!("8dBNWFxxdvHmoZWKuS1rGzGGmBxdXxHXauYAiTPM4Zan");
 
@@ -7,6 +11,10 @@ declare_id!("8dBNWFxxdvHmoZWKuS1rGzGGmBxdXxHXauYAiTPM4Zan");
 /// Architecture:
 /// - Pool: Public state (TVL, loan count, interest rate)
 /// - Position: Encrypted state (collateral, debt, encrypted via Inco)
+/// - Vaults: Pool-owned SPL token accounts, one for collateral and one for
+///   borrowable liquidity, each with its own accounting on the pool
+/// - Share Mint: Interest-bearing lender shares (cToken-style)
+/// - Oracle: Collateral price in borrow-asset units, read through an adapter
 /// - All sensitive data stored as encrypted bytes (32-byte ciphertext)
 /// 
 /// Privacy Model:
@@ -19,102 +27,153 @@ declare_id!("8dBNWFxxdvHmoZWKuS1rGzGGmBxdXxHXauYAiTPM4Zan");
 pub mod shadow_lend {
     use super::*;
 
-    /// Initialize the lending pool with configuration
+    /// Initialize a lending pool with configuration
+    /// Pools are keyed by asset pair and `pool_id`, so one program can list
+    /// several markets (and several markets for the same pair)
     pub fn initialize_pool(
         ctx: Context<InitializePool>,
-        collateral_mint: Pubkey,
-        borrow_mint: Pubkey,
-        ltv_ratio: u16,           // Loan-to-value ratio (basis points, e.g., 7500 = 75%)
-        interest_rate: u16,       // Annual interest rate (basis points)
-        liquidation_threshold: u16, // Threshold for liquidation (basis points)
+        pool_id: u32,
+        config: PoolConfig, // LTV, liquidation and rate parameters (basis points)
     ) -> Result<()> {
+        config.validate()?;
+
         let pool = &mut ctx.accounts.pool;
         
         pool.authority = ctx.accounts.authority.key();
-        pool.collateral_mint = collateral_mint;
-        pool.borrow_mint = borrow_mint;
-        pool.ltv_ratio = ltv_ratio;
-        pool.interest_rate = interest_rate;
-        pool.liquidation_threshold = liquidation_threshold;
+        pool.collateral_mint = ctx.accounts.collateral_mint.key();
+        pool.borrow_mint = ctx.accounts.borrow_mint.key();
+        pool.collateral_decimals = ctx.accounts.collateral_mint.decimals;
+        pool.borrow_decimals = ctx.accounts.borrow_mint.decimals;
+        pool.pool_id = pool_id;
+        pool.apply_config(&config);
+        pool.pending_insurance = 0;
+        pool.protocol_reserves = 0;
+        pool.treasury = Pubkey::default();
+        pool.total_collateral = 0;
         pool.total_deposits = 0;
         pool.total_borrows = 0;
+        pool.borrow_index = INDEX_SCALE;
+        pool.interest_remainder = 0;
+        pool.last_accrual_ts = Clock::get()?.unix_timestamp;
         pool.active_positions = 0;
+        pool.attesters = [Pubkey::default(); MAX_ATTESTERS];
+        pool.attester_count = 0;
+        pool.pending_authority = Pubkey::default();
+        pool.guardian = ctx.accounts.authority.key();
+        pool.risk_admin = ctx.accounts.authority.key();
+        pool.attester_manager = ctx.accounts.authority.key();
+        pool.pause_flags = 0;
         pool.bump = ctx.bumps.pool;
-        pool.is_active = true;
 
         emit!(PoolInitialized {
             pool: pool.key(),
-            collateral_mint,
-            borrow_mint,
-            ltv_ratio,
-            interest_rate,
+            collateral_mint: pool.collateral_mint,
+            borrow_mint: pool.borrow_mint,
+            pool_id,
+            config,
+        });
+
+        msg!("Pool initialized: LTV={}%, Base rate={}%, Optimal utilization={}%", 
+            config.ltv_ratio as f64 / 100.0, 
+            config.rate_model.base_rate as f64 / 100.0,
+            config.rate_model.optimal_utilization as f64 / 100.0);
+        Ok(())
+    }
+
+    /// Create the pool-owned collateral vault and liquidity vault
+    /// Collateral and borrowable liquidity are never commingled, even when
+    /// both sides of the pool use the same mint
+    pub fn initialize_vaults(ctx: Context<InitializeVaults>) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        pool.share_mint = ctx.accounts.share_mint.key();
+
+        emit!(VaultsInitialized {
+            pool: pool.key(),
+            collateral_vault: ctx.accounts.collateral_vault.key(),
+            liquidity_vault: ctx.accounts.liquidity_vault.key(),
+            insurance_vault: ctx.accounts.insurance_vault.key(),
+            share_mint: pool.share_mint,
         });
 
-        msg!("Pool initialized: LTV={}%, Interest={}%", 
-            ltv_ratio as f64 / 100.0, 
-            interest_rate as f64 / 100.0);
         Ok(())
     }
 
     /// Open a new position for a user
     /// Position data is encrypted client-side before submission
-    pub fn open_position(ctx: Context<OpenPosition>) -> Result<()> {
+    /// `position_index` lets one wallet hold several isolated positions per pool
+    pub fn open_position(ctx: Context<OpenPosition>, position_index: u32) -> Result<()> {
+        ctx.accounts.pool.require_not_paused(PAUSE_DEPOSITS)?;
+
         let position = &mut ctx.accounts.position;
-        let pool = &ctx.accounts.pool;
+        let pool = &mut ctx.accounts.pool;
         
         position.owner = ctx.accounts.owner.key();
         position.pool = pool.key();
+        position.index = position_index;
         position.encrypted_collateral = [0u8; 32]; // Placeholder for Inco ciphertext
         position.encrypted_debt = [0u8; 32];
+        position.debt_index = pool.borrow_index;
+        position.nonce = 0;
         position.last_update = Clock::get()?.unix_timestamp;
         position.is_active = true;
         position.bump = ctx.bumps.position;
 
         // Increment pool position count
-        let pool_account = &mut ctx.accounts.pool_mut;
-        pool_account.active_positions = pool_account.active_positions.checked_add(1).unwrap();
+        pool.active_positions = pool.active_positions.checked_add(1).unwrap();
 
         emit!(PositionOpened {
             position: position.key(),
             owner: position.owner,
             pool: pool.key(),
+            index: position_index,
         });
 
         Ok(())
     }
 
-    /// Deposit collateral (amount is encrypted)
-    /// Client encrypts the amount before calling this
+    /// Deposit collateral (position balance is encrypted)
+    /// Client encrypts the new collateral total and Inco attests new = old + amount
     pub fn deposit_collateral(
         ctx: Context<DepositCollateral>,
         amount: u64,
-        encrypted_amount: [u8; 32], // Inco-encrypted amount
+        encrypted_new_collateral: [u8; 32], // New encrypted collateral total
+        inco_proof: Vec<u8>,                // Proof from Inco that new = old + amount
     ) -> Result<()> {
         require!(amount > 0, ShadowLendError::InvalidAmount);
+        ctx.accounts.pool.require_not_paused(PAUSE_DEPOSITS)?;
+        accrue_pool_interest(&mut ctx.accounts.pool)?;
 
-        // Transfer SOL as collateral
-        let transfer_ix = anchor_lang::solana_program::system_instruction::transfer(
-            &ctx.accounts.owner.key(),
-            &ctx.accounts.vault.key(),
-            amount,
+        // Verify Inco proof
+        require!(
+            verify_inco_deposit_proof(
+                &inco_proof,
+                &ctx.accounts.pool,
+                &ctx.accounts.position,
+                amount,
+                &encrypted_new_collateral,
+                &ctx.accounts.instructions,
+            ),
+            ShadowLendError::InvalidIncoProof
         );
-        
-        anchor_lang::solana_program::program::invoke(
-            &transfer_ix,
-            &[
-                ctx.accounts.owner.to_account_info(),
-                ctx.accounts.vault.to_account_info(),
-            ],
+
+        // Transfer collateral tokens into the vault
+        transfer_to_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.owner_collateral_account,
+            &ctx.accounts.collateral_vault,
+            &ctx.accounts.owner,
+            amount,
         )?;
 
-        // Update position with encrypted amount
+        // Update position with new encrypted collateral and consume the proof
         let position = &mut ctx.accounts.position;
-        position.encrypted_collateral = encrypted_amount;
+        position.encrypted_collateral = encrypted_new_collateral;
+        position.nonce = position.nonce.checked_add(1).unwrap();
         position.last_update = Clock::get()?.unix_timestamp;
 
-        // Update pool TVL (public aggregate)
+        // Update pool collateral reserve (public aggregate)
         let pool = &mut ctx.accounts.pool;
-        pool.total_deposits = pool.total_deposits.checked_add(amount).unwrap();
+        pool.total_collateral = pool.total_collateral.checked_add(amount).unwrap();
 
         emit!(CollateralDeposited {
             position: position.key(),
@@ -135,75 +194,138 @@ pub mod shadow_lend {
         inco_proof: Vec<u8>,          // Proof from Inco that borrow is valid
     ) -> Result<()> {
         require!(amount > 0, ShadowLendError::InvalidAmount);
+        ctx.accounts.pool.require_not_paused(PAUSE_BORROWS)?;
+        accrue_pool_interest(&mut ctx.accounts.pool)?;
+        let price = ctx.accounts.pool.oracle.load_price(&ctx.accounts.oracle)?;
         
+        // The origination fee is added to the borrower's debt, not deducted
+        // from the disbursed amount
+        let fee = ctx.accounts.pool.origination_fee_for(amount)?;
+
         // Verify Inco proof
         require!(
-            verify_inco_borrow_proof(&inco_proof, &ctx.accounts.position, amount),
+            verify_inco_borrow_proof(
+                &inco_proof,
+                &ctx.accounts.pool,
+                &ctx.accounts.position,
+                amount,
+                fee,
+                &encrypted_new_debt,
+                &price,
+                &ctx.accounts.instructions,
+            ),
             ShadowLendError::InvalidIncoProof
         );
 
-        let vault_balance = ctx.accounts.vault.lamports();
-        require!(vault_balance >= amount, ShadowLendError::InsufficientLiquidity);
+        // Borrows can only draw on lender-supplied liquidity
+        require!(
+            ctx.accounts.pool.available_liquidity() >= amount
+                && ctx.accounts.liquidity_vault.amount >= amount,
+            ShadowLendError::InsufficientLiquidity
+        );
 
-        // Transfer from vault to borrower
-        **ctx.accounts.vault.try_borrow_mut_lamports()? -= amount;
-        **ctx.accounts.owner.try_borrow_mut_lamports()? += amount;
+        // Transfer from liquidity vault to borrower
+        transfer_from_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.pool,
+            &ctx.accounts.liquidity_vault,
+            &ctx.accounts.owner_borrow_account,
+            amount,
+        )?;
 
-        // Update position with new encrypted debt
+        // Update position with new encrypted debt and consume the proof
         let position = &mut ctx.accounts.position;
         position.encrypted_debt = encrypted_new_debt;
+        position.debt_index = ctx.accounts.pool.borrow_index;
+        position.nonce = position.nonce.checked_add(1).unwrap();
         position.last_update = Clock::get()?.unix_timestamp;
 
-        // Update pool borrows
-        let pool = &mut ctx.accounts.pool_mut;
-        pool.total_borrows = pool.total_borrows.checked_add(amount).unwrap();
+        // Update pool borrows; the fee is owed to protocol reserves
+        let pool = &mut ctx.accounts.pool;
+        pool.total_borrows = amount
+            .checked_add(fee)
+            .and_then(|debt| pool.total_borrows.checked_add(debt))
+            .ok_or(ShadowLendError::MathOverflow)?;
+        pool.protocol_reserves = pool
+            .protocol_reserves
+            .checked_add(fee)
+            .ok_or(ShadowLendError::MathOverflow)?;
 
         emit!(Borrowed {
             position: position.key(),
             owner: position.owner,
             amount,
+            fee,
             timestamp: position.last_update,
         });
 
+        if fee > 0 {
+            emit!(ReservesAccrued {
+                pool: pool.key(),
+                amount: fee,
+                protocol_reserves: pool.protocol_reserves,
+                timestamp: position.last_update,
+            });
+        }
+
         Ok(())
     }
 
     /// Repay borrowed amount
+    /// Only `applied_amount` = min(amount, debt), as attested by Inco, is
+    /// transferred and removed from total_borrows
     pub fn repay(
         ctx: Context<Repay>,
-        amount: u64,
+        amount: u64,                  // Maximum the owner is willing to repay
+        applied_amount: u64,          // min(amount, debt), covered by the proof
         encrypted_new_debt: [u8; 32], // Updated encrypted debt after repayment
+        inco_proof: Vec<u8>,          // Proof from Inco that new debt = old debt - applied
     ) -> Result<()> {
-        require!(amount > 0, ShadowLendError::InvalidAmount);
+        require!(
+            applied_amount > 0 && applied_amount <= amount,
+            ShadowLendError::InvalidAmount
+        );
+        ctx.accounts.pool.require_not_paused(PAUSE_REPAYMENTS)?;
+        accrue_pool_interest(&mut ctx.accounts.pool)?;
 
-        // Transfer repayment to vault
-        let transfer_ix = anchor_lang::solana_program::system_instruction::transfer(
-            &ctx.accounts.owner.key(),
-            &ctx.accounts.vault.key(),
-            amount,
+        // Verify Inco proof
+        require!(
+            verify_inco_repay_proof(
+                &inco_proof,
+                &ctx.accounts.pool,
+                &ctx.accounts.position,
+                amount,
+                applied_amount,
+                &encrypted_new_debt,
+                &ctx.accounts.instructions,
+            ),
+            ShadowLendError::InvalidIncoProof
         );
-        
-        anchor_lang::solana_program::program::invoke(
-            &transfer_ix,
-            &[
-                ctx.accounts.owner.to_account_info(),
-                ctx.accounts.vault.to_account_info(),
-            ],
+
+        // Transfer repayment to liquidity vault
+        transfer_to_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.owner_borrow_account,
+            &ctx.accounts.liquidity_vault,
+            &ctx.accounts.owner,
+            applied_amount,
         )?;
 
-        // Update position
+        // Update position with new encrypted debt and consume the proof
         let position = &mut ctx.accounts.position;
         position.encrypted_debt = encrypted_new_debt;
+        position.debt_index = ctx.accounts.pool.borrow_index;
+        position.nonce = position.nonce.checked_add(1).unwrap();
         position.last_update = Clock::get()?.unix_timestamp;
 
         // Update pool
         let pool = &mut ctx.accounts.pool;
-        pool.total_borrows = pool.total_borrows.saturating_sub(amount);
+        pool.total_borrows = pool.total_borrows.saturating_sub(applied_amount);
 
         emit!(Repaid {
             position: position.key(),
             owner: position.owner,
-            amount,
+            amount: applied_amount,
             timestamp: position.last_update,
         });
 
@@ -219,28 +341,49 @@ pub mod shadow_lend {
         inco_proof: Vec<u8>,
     ) -> Result<()> {
         require!(amount > 0, ShadowLendError::InvalidAmount);
+        ctx.accounts.pool.require_not_paused(PAUSE_WITHDRAWALS)?;
+        accrue_pool_interest(&mut ctx.accounts.pool)?;
+        let price = ctx.accounts.pool.oracle.load_price(&ctx.accounts.oracle)?;
 
         // Verify Inco proof that withdrawal maintains healthy position
         require!(
-            verify_inco_withdrawal_proof(&inco_proof, &ctx.accounts.position, amount),
+            verify_inco_withdrawal_proof(
+                &inco_proof,
+                &ctx.accounts.pool,
+                &ctx.accounts.position,
+                amount,
+                &encrypted_new_collateral,
+                &price,
+                &ctx.accounts.instructions,
+            ),
             ShadowLendError::InvalidIncoProof
         );
 
-        let vault_balance = ctx.accounts.vault.lamports();
-        require!(vault_balance >= amount, ShadowLendError::InsufficientLiquidity);
+        // Withdrawals can only return collateral, never lender liquidity
+        require!(
+            ctx.accounts.pool.total_collateral >= amount
+                && ctx.accounts.collateral_vault.amount >= amount,
+            ShadowLendError::InsufficientCollateral
+        );
 
-        // Transfer from vault to owner
-        **ctx.accounts.vault.try_borrow_mut_lamports()? -= amount;
-        **ctx.accounts.owner.try_borrow_mut_lamports()? += amount;
+        // Transfer from collateral vault to owner
+        transfer_from_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.pool,
+            &ctx.accounts.collateral_vault,
+            &ctx.accounts.owner_collateral_account,
+            amount,
+        )?;
 
-        // Update position
+        // Update position and consume the proof
         let position = &mut ctx.accounts.position;
         position.encrypted_collateral = encrypted_new_collateral;
+        position.nonce = position.nonce.checked_add(1).unwrap();
         position.last_update = Clock::get()?.unix_timestamp;
 
         // Update pool
         let pool = &mut ctx.accounts.pool;
-        pool.total_deposits = pool.total_deposits.saturating_sub(amount);
+        pool.total_collateral = pool.total_collateral.checked_sub(amount).unwrap();
 
         emit!(CollateralWithdrawn {
             position: position.key(),
@@ -252,58 +395,238 @@ pub mod shadow_lend {
         Ok(())
     }
 
-    /// Liquidate an unhealthy position
-    /// Requires Inco proof that health factor < liquidation threshold
+    /// Partially liquidate an unhealthy position
+    /// The liquidator repays `repay_amount` of the borrow asset and receives
+    /// the equivalent collateral plus the liquidation bonus
+    /// Requires Inco proof that health factor < liquidation threshold, that
+    /// `repay_amount` is within the close factor of the debt, and that the new
+    /// ciphertexts encrypt debt - repay_amount and collateral - seized amount
     pub fn liquidate(
         ctx: Context<Liquidate>,
+        repay_amount: u64,
+        encrypted_new_collateral: [u8; 32],
+        encrypted_new_debt: [u8; 32],
         inco_proof: Vec<u8>,
     ) -> Result<()> {
-        // Verify position is indeed underwater
+        require!(repay_amount > 0, ShadowLendError::InvalidAmount);
+        ctx.accounts.pool.require_not_paused(PAUSE_LIQUIDATIONS)?;
+        accrue_pool_interest(&mut ctx.accounts.pool)?;
+        let price = ctx.accounts.pool.oracle.load_price(&ctx.accounts.oracle)?;
+
+        // Collateral owed to the liquidator, including the bonus
+        let seize_amount = ctx
+            .accounts
+            .pool
+            .liquidation_seize_amount(repay_amount, &price)
+            .ok_or(ShadowLendError::MathOverflow)?;
+
+        // Verify position is indeed underwater and the new state is consistent
         require!(
-            verify_inco_liquidation_proof(&inco_proof, &ctx.accounts.position),
+            verify_inco_liquidation_proof(
+                &inco_proof,
+                &ctx.accounts.pool,
+                &ctx.accounts.position,
+                repay_amount,
+                seize_amount,
+                &encrypted_new_collateral,
+                &encrypted_new_debt,
+                &price,
+                &ctx.accounts.instructions,
+            ),
             ShadowLendError::PositionHealthy
         );
 
-        let position = &mut ctx.accounts.position;
-        position.is_active = false;
+        require!(
+            ctx.accounts.pool.total_collateral >= seize_amount
+                && ctx.accounts.collateral_vault.amount >= seize_amount,
+            ShadowLendError::InsufficientCollateral
+        );
 
-        // Transfer remaining collateral to liquidator (simplified)
-        let vault_balance = ctx.accounts.vault.lamports();
-        let rent = Rent::get()?.minimum_balance(0);
-        let transferable = vault_balance.saturating_sub(rent);
-        
-        if transferable > 0 {
-            **ctx.accounts.vault.try_borrow_mut_lamports()? -= transferable;
-            **ctx.accounts.liquidator.try_borrow_mut_lamports()? += transferable;
-        }
+        // Liquidator repays part of the debt into the liquidity vault first
+        transfer_to_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.liquidator_borrow_account,
+            &ctx.accounts.liquidity_vault,
+            &ctx.accounts.liquidator,
+            repay_amount,
+        )?;
+
+        // Then receives the seized collateral
+        transfer_from_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.pool,
+            &ctx.accounts.collateral_vault,
+            &ctx.accounts.liquidator_collateral_account,
+            seize_amount,
+        )?;
+
+        // Update position and consume the proof
+        let position = &mut ctx.accounts.position;
+        position.encrypted_collateral = encrypted_new_collateral;
+        position.encrypted_debt = encrypted_new_debt;
+        position.debt_index = ctx.accounts.pool.borrow_index;
+        position.nonce = position.nonce.checked_add(1).unwrap();
+        position.last_update = Clock::get()?.unix_timestamp;
 
         // Update pool
         let pool = &mut ctx.accounts.pool;
-        pool.active_positions = pool.active_positions.saturating_sub(1);
+        pool.total_borrows = pool.total_borrows.saturating_sub(repay_amount);
+        pool.total_collateral = pool.total_collateral.checked_sub(seize_amount).unwrap();
 
         emit!(PositionLiquidated {
             position: position.key(),
             owner: position.owner,
             liquidator: ctx.accounts.liquidator.key(),
-            timestamp: Clock::get()?.unix_timestamp,
+            repay_amount,
+            seize_amount,
+            timestamp: position.last_update,
         });
 
         Ok(())
     }
 
-    /// Close an empty position
-    pub fn close_position(ctx: Context<ClosePosition>) -> Result<()> {
-        let position = &ctx.accounts.position;
-        
-        // Ensure position is empty (debt = 0)
+    /// Write off the remaining debt of a position whose collateral is gone
+    /// Requires Inco proof that the position's outstanding debt equals
+    /// `shortfall`, that its remaining collateral equals `residual_collateral`
+    /// (liquidation rounding can leave dust) and that both new ciphertexts
+    /// encrypt zero. The residual must be worth less than the shortfall at the
+    /// oracle price; the keeper buys it at that value, which is netted against
+    /// the loss. The insurance fund covers as much of the rest as it can and
+    /// the remainder is socialized across suppliers by lowering the share
+    /// exchange rate.
+    pub fn settle_bad_debt(
+        ctx: Context<SettleBadDebt>,
+        shortfall: u64,
+        residual_collateral: u64,
+        encrypted_new_collateral: [u8; 32],
+        encrypted_new_debt: [u8; 32],
+        inco_proof: Vec<u8>,
+    ) -> Result<()> {
+        require!(shortfall > 0, ShadowLendError::InvalidAmount);
+        ctx.accounts.pool.require_not_paused(PAUSE_LIQUIDATIONS)?;
+        accrue_pool_interest(&mut ctx.accounts.pool)?;
+        let price = ctx.accounts.pool.oracle.load_price(&ctx.accounts.oracle)?;
+
+        require!(
+            verify_inco_bad_debt_proof(
+                &inco_proof,
+                &ctx.accounts.pool,
+                &ctx.accounts.position,
+                shortfall,
+                residual_collateral,
+                &encrypted_new_collateral,
+                &encrypted_new_debt,
+                &price,
+                &ctx.accounts.instructions,
+            ),
+            ShadowLendError::InvalidIncoProof
+        );
         require!(
-            position.encrypted_debt == [0u8; 32],
-            ShadowLendError::PositionHasDebt
+            ctx.accounts.pool.total_collateral >= residual_collateral
+                && ctx.accounts.collateral_vault.amount >= residual_collateral,
+            ShadowLendError::InsufficientCollateral
         );
 
-        // Update pool
+        // Only positions whose collateral cannot cover the debt are written off
+        let residual_value = ctx
+            .accounts
+            .pool
+            .collateral_value(residual_collateral, &price)
+            .ok_or(ShadowLendError::MathOverflow)?;
+        require!(residual_value < shortfall, ShadowLendError::PositionNotInsolvent);
+
+        // Keeper buys the residual collateral at its oracle value
+        if residual_value > 0 {
+            transfer_to_vault(
+                &ctx.accounts.token_program,
+                &ctx.accounts.keeper_borrow_account,
+                &ctx.accounts.liquidity_vault,
+                &ctx.accounts.keeper,
+                residual_value,
+            )?;
+        }
+        if residual_collateral > 0 {
+            transfer_from_vault(
+                &ctx.accounts.token_program,
+                &ctx.accounts.pool,
+                &ctx.accounts.collateral_vault,
+                &ctx.accounts.keeper_collateral_account,
+                residual_collateral,
+            )?;
+        }
+        let loss = shortfall - residual_value;
+
+        // Cover the rest from the insurance fund first
+        let covered = loss.min(ctx.accounts.insurance_vault.amount);
+        if covered > 0 {
+            transfer_from_vault(
+                &ctx.accounts.token_program,
+                &ctx.accounts.pool,
+                &ctx.accounts.insurance_vault,
+                &ctx.accounts.liquidity_vault,
+                covered,
+            )?;
+        }
+        let socialized = loss - covered;
+
+        // Close out the position
+        let position = &mut ctx.accounts.position;
+        position.encrypted_collateral = encrypted_new_collateral;
+        position.encrypted_debt = encrypted_new_debt;
+        position.debt_index = ctx.accounts.pool.borrow_index;
+        position.nonce = position.nonce.checked_add(1).unwrap();
+        position.is_active = false;
+        position.last_update = Clock::get()?.unix_timestamp;
+
+        // Write off the debt; suppliers absorb what insurance could not
         let pool = &mut ctx.accounts.pool;
+        pool.total_borrows = pool.total_borrows.saturating_sub(shortfall);
+        pool.total_deposits = pool
+            .total_deposits
+            .checked_sub(socialized)
+            .ok_or(ShadowLendError::BadDebtExceedsDeposits)?;
         pool.active_positions = pool.active_positions.saturating_sub(1);
+        pool.total_collateral -= residual_collateral;
+
+        emit!(BadDebtSettled {
+            pool: pool.key(),
+            position: position.key(),
+            keeper: ctx.accounts.keeper.key(),
+            shortfall,
+            residual_collateral,
+            residual_value,
+            covered,
+            socialized,
+            timestamp: position.last_update,
+        });
+
+        Ok(())
+    }
+
+    /// Close an empty position
+    pub fn close_position(
+        ctx: Context<ClosePosition>,
+        inco_proof: Vec<u8>, // Proof from Inco that collateral and debt are both zero
+    ) -> Result<()> {
+        let position = &ctx.accounts.position;
+
+        // Ciphertexts can't be compared against zero on-chain; Inco attests
+        // that both encrypted balances decrypt to zero
+        require!(
+            verify_inco_close_proof(
+                &inco_proof,
+                &ctx.accounts.pool,
+                position,
+                &ctx.accounts.instructions,
+            ),
+            ShadowLendError::InvalidIncoProof
+        );
+
+        // Positions settled as bad debt were already removed from the count
+        if position.is_active {
+            let pool = &mut ctx.accounts.pool;
+            pool.active_positions = pool.active_positions.saturating_sub(1);
+        }
 
         emit!(PositionClosed {
             position: position.key(),
@@ -312,231 +635,1895 @@ pub mod shadow_lend {
 
         Ok(())
     }
-}
 
-// ============================================================================
-// Account Structures
-// ============================================================================
+    /// Supply borrowable liquidity in exchange for pool shares
+    /// Shares appreciate as borrowers pay interest into the pool
+    pub fn supply(ctx: Context<Supply>, amount: u64) -> Result<()> {
+        require!(amount > 0, ShadowLendError::InvalidAmount);
+        ctx.accounts.pool.require_not_paused(PAUSE_DEPOSITS)?;
+        accrue_pool_interest(&mut ctx.accounts.pool)?;
 
-#[derive(Accounts)]
-pub struct InitializePool<'info> {
-    #[account(
-        init,
-        payer = authority,
-        space = 8 + Pool::INIT_SPACE,
-        seeds = [b"pool"],
-        bump
-    )]
-    pub pool: Account<'info, Pool>,
-    
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    
-    pub system_program: Program<'info, System>,
-}
+        let share_supply = ctx.accounts.share_mint.supply;
+        require!(
+            share_supply == 0 || ctx.accounts.pool.total_deposits > 0,
+            ShadowLendError::UnbackedShares
+        );
+        let shares = ctx
+            .accounts
+            .pool
+            .shares_for_amount(amount, share_supply)
+            .ok_or(ShadowLendError::MathOverflow)?;
+        require!(shares > 0, ShadowLendError::InvalidAmount);
 
-#[derive(Accounts)]
-pub struct OpenPosition<'info> {
-    #[account(
-        seeds = [b"pool"],
-        bump = pool.bump
-    )]
-    pub pool: Account<'info, Pool>,
+        transfer_to_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.supplier_borrow_account,
+            &ctx.accounts.liquidity_vault,
+            &ctx.accounts.supplier,
+            amount,
+        )?;
 
-    #[account(
-        mut,
-        seeds = [b"pool"],
-        bump = pool.bump
-    )]
-    pub pool_mut: Account<'info, Pool>,
-    
-    #[account(
-        init,
-        payer = owner,
-        space = 8 + Position::INIT_SPACE,
-        seeds = [b"position", owner.key().as_ref()],
-        bump
-    )]
-    pub position: Account<'info, Position>,
-    
-    #[account(mut)]
-    pub owner: Signer<'info>,
-    
-    pub system_program: Program<'info, System>,
-}
+        let pool = &ctx.accounts.pool;
+        let pool_id = pool.pool_id.to_le_bytes();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"pool",
+            pool.collateral_mint.as_ref(),
+            pool.borrow_mint.as_ref(),
+            &pool_id,
+            &[pool.bump],
+        ]];
+        token::mint_to(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                MintTo {
+                    mint: ctx.accounts.share_mint.to_account_info(),
+                    to: ctx.accounts.supplier_share_account.to_account_info(),
+                    authority: pool.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            shares,
+        )?;
 
-#[derive(Accounts)]
-pub struct DepositCollateral<'info> {
-    #[account(mut)]
-    pub pool: Account<'info, Pool>,
-    
-    #[account(
-        mut,
-        seeds = [b"position", owner.key().as_ref()],
-        bump = position.bump,
-        constraint = position.owner == owner.key()
-    )]
-    pub position: Account<'info, Position>,
-    
-    /// CHECK: Vault PDA for holding collateral
-    #[account(
-        mut,
-        seeds = [b"vault", pool.key().as_ref()],
-        bump
-    )]
-    pub vault: AccountInfo<'info>,
-    
-    #[account(mut)]
-    pub owner: Signer<'info>,
-    
-    pub system_program: Program<'info, System>,
-}
+        let pool = &mut ctx.accounts.pool;
+        pool.total_deposits = pool.total_deposits.checked_add(amount).unwrap();
 
-#[derive(Accounts)]
-pub struct Borrow<'info> {
-    pub pool: Account<'info, Pool>,
+        emit!(Supplied {
+            pool: pool.key(),
+            supplier: ctx.accounts.supplier.key(),
+            amount,
+            shares,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
 
-    #[account(mut)]
-    pub pool_mut: Account<'info, Pool>,
-    
-    #[account(
-        mut,
-        seeds = [b"position", owner.key().as_ref()],
-        bump = position.bump,
-        constraint = position.owner == owner.key()
-    )]
-    pub position: Account<'info, Position>,
-    
-    /// CHECK: Vault PDA
-    #[account(
-        mut,
-        seeds = [b"vault", pool.key().as_ref()],
-        bump
-    )]
-    pub vault: AccountInfo<'info>,
-    
-    #[account(mut)]
-    pub owner: Signer<'info>,
-    
-    pub system_program: Program<'info, System>,
-}
+        Ok(())
+    }
 
-#[derive(Accounts)]
-pub struct Repay<'info> {
-    #[account(mut)]
-    pub pool: Account<'info, Pool>,
-    
-    #[account(
-        mut,
-        seeds = [b"position", owner.key().as_ref()],
-        bump = position.bump,
-        constraint = position.owner == owner.key()
-    )]
-    pub position: Account<'info, Position>,
-    
-    /// CHECK: Vault PDA
-    #[account(
-        mut,
-        seeds = [b"vault", pool.key().as_ref()],
-        bump
-    )]
-    pub vault: AccountInfo<'info>,
-    
-    #[account(mut)]
-    pub owner: Signer<'info>,
-    
-    pub system_program: Program<'info, System>,
-}
+    /// Burn pool shares and withdraw the underlying liquidity
+    pub fn redeem(ctx: Context<Redeem>, shares: u64) -> Result<()> {
+        require!(shares > 0, ShadowLendError::InvalidAmount);
+        ctx.accounts.pool.require_not_paused(PAUSE_WITHDRAWALS)?;
+        accrue_pool_interest(&mut ctx.accounts.pool)?;
 
-#[derive(Accounts)]
-pub struct WithdrawCollateral<'info> {
-    #[account(mut)]
-    pub pool: Account<'info, Pool>,
-    
-    #[account(
-        mut,
-        seeds = [b"position", owner.key().as_ref()],
-        bump = position.bump,
-        constraint = position.owner == owner.key()
-    )]
-    pub position: Account<'info, Position>,
-    
-    /// CHECK: Vault PDA
-    #[account(
-        mut,
-        seeds = [b"vault", pool.key().as_ref()],
-        bump
-    )]
-    pub vault: AccountInfo<'info>,
-    
-    #[account(mut)]
-    pub owner: Signer<'info>,
-    
-    pub system_program: Program<'info, System>,
-}
+        let amount = ctx
+            .accounts
+            .pool
+            .amount_for_shares(shares, ctx.accounts.share_mint.supply)
+            .ok_or(ShadowLendError::MathOverflow)?;
+        require!(amount > 0, ShadowLendError::InvalidAmount);
+        require!(
+            ctx.accounts.pool.available_liquidity() >= amount
+                && ctx.accounts.liquidity_vault.amount >= amount,
+            ShadowLendError::InsufficientLiquidity
+        );
 
-#[derive(Accounts)]
-pub struct Liquidate<'info> {
-    #[account(mut)]
-    pub pool: Account<'info, Pool>,
-    
-    #[account(
-        mut,
-        constraint = position.is_active
+        token::burn(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Burn {
+                    mint: ctx.accounts.share_mint.to_account_info(),
+                    from: ctx.accounts.supplier_share_account.to_account_info(),
+                    authority: ctx.accounts.supplier.to_account_info(),
+                },
+            ),
+            shares,
+        )?;
+
+        transfer_from_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.pool,
+            &ctx.accounts.liquidity_vault,
+            &ctx.accounts.supplier_borrow_account,
+            amount,
+        )?;
+
+        let pool = &mut ctx.accounts.pool;
+        pool.total_deposits = pool.total_deposits.checked_sub(amount).unwrap();
+
+        emit!(Redeemed {
+            pool: pool.key(),
+            supplier: ctx.accounts.supplier.key(),
+            amount,
+            shares,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Accrue interest on outstanding borrows
+    /// Permissionless so keepers can keep the borrow index fresh on idle pools
+    pub fn accrue_interest(ctx: Context<AccrueInterest>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let pool = &mut ctx.accounts.pool;
+        let interest = accrue_pool_interest(pool)?;
+
+        emit!(InterestAccrued {
+            pool: pool.key(),
+            interest,
+            borrow_index: pool.borrow_index,
+            total_borrows: pool.total_borrows,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    /// Move the insurance share of accrued interest into the insurance fund
+    /// Permissionless; limited by the idle liquidity currently in the pool
+    pub fn sweep_insurance(ctx: Context<SweepInsurance>) -> Result<()> {
+        accrue_pool_interest(&mut ctx.accounts.pool)?;
+
+        let pool = &ctx.accounts.pool;
+        let amount = pool
+            .pending_insurance
+            .min(pool.available_liquidity())
+            .min(ctx.accounts.liquidity_vault.amount);
+        require!(amount > 0, ShadowLendError::InvalidAmount);
+
+        transfer_from_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.pool,
+            &ctx.accounts.liquidity_vault,
+            &ctx.accounts.insurance_vault,
+            amount,
+        )?;
+
+        let pool = &mut ctx.accounts.pool;
+        pool.pending_insurance = pool.pending_insurance.checked_sub(amount).unwrap();
+
+        emit!(InsuranceFunded {
+            pool: pool.key(),
+            amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Point reserve withdrawals at a borrow-mint token account
+    pub fn set_treasury(ctx: Context<SetTreasury>) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        pool.treasury = ctx.accounts.treasury.key();
+
+        emit!(TreasuryUpdated {
+            pool: pool.key(),
+            treasury: pool.treasury,
+        });
+
+        Ok(())
+    }
+
+    /// Withdraw accumulated protocol reserves to the configured treasury
+    pub fn withdraw_reserves(ctx: Context<WithdrawReserves>, amount: u64) -> Result<()> {
+        require!(amount > 0, ShadowLendError::InvalidAmount);
+        accrue_pool_interest(&mut ctx.accounts.pool)?;
+
+        let pool = &ctx.accounts.pool;
+        require!(
+            amount <= pool.protocol_reserves,
+            ShadowLendError::InsufficientReserves
+        );
+        require!(
+            amount <= pool.available_liquidity(),
+            ShadowLendError::InsufficientLiquidity
+        );
+
+        transfer_from_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.pool,
+            &ctx.accounts.liquidity_vault,
+            &ctx.accounts.treasury,
+            amount,
+        )?;
+
+        let pool = &mut ctx.accounts.pool;
+        pool.protocol_reserves -= amount;
+
+        emit!(ReservesWithdrawn {
+            pool: pool.key(),
+            treasury: pool.treasury,
+            amount,
+            protocol_reserves: pool.protocol_reserves,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Create a mock price feed for localnet and tests
+    /// Fails unless the program is built with the `mock-oracle` feature
+    pub fn initialize_mock_price_feed(
+        ctx: Context<InitializeMockPriceFeed>,
+        price: i64,
+        conf: u64,
+        expo: i32,
+    ) -> Result<()> {
+        require!(MOCK_ORACLE_ENABLED, ShadowLendError::MockOracleDisabled);
+
+        let feed = &mut ctx.accounts.price_feed;
+        feed.authority = ctx.accounts.authority.key();
+        feed.price = price;
+        feed.conf = conf;
+        feed.expo = expo;
+        feed.publish_slot = Clock::get()?.slot;
+        Ok(())
+    }
+
+    /// Publish a new price on a mock price feed
+    pub fn set_mock_price(
+        ctx: Context<SetMockPrice>,
+        price: i64,
+        conf: u64,
+        expo: i32,
+    ) -> Result<()> {
+        require!(MOCK_ORACLE_ENABLED, ShadowLendError::MockOracleDisabled);
+
+        let feed = &mut ctx.accounts.price_feed;
+        feed.price = price;
+        feed.conf = conf;
+        feed.expo = expo;
+        feed.publish_slot = Clock::get()?.slot;
+        Ok(())
+    }
+
+    /// Replace the set of Inco attester keys trusted by this pool
+    /// Gated by the attester manager role
+    pub fn set_attesters(ctx: Context<SetAttesters>, attesters: Vec<Pubkey>) -> Result<()> {
+        require!(
+            attesters.len() <= MAX_ATTESTERS,
+            ShadowLendError::TooManyAttesters
+        );
+
+        let pool = &mut ctx.accounts.pool;
+        pool.attesters = [Pubkey::default(); MAX_ATTESTERS];
+        pool.attesters[..attesters.len()].copy_from_slice(&attesters);
+        pool.attester_count = attesters.len() as u8;
+
+        emit!(AttestersUpdated {
+            pool: pool.key(),
+            attesters,
+        });
+
+        Ok(())
+    }
+
+    /// Set which operations are paused (PAUSE_* bits)
+    /// Lets the guardian halt e.g. borrows while repayments and exits continue
+    pub fn set_pause_flags(ctx: Context<SetPauseFlags>, pause_flags: u8) -> Result<()> {
+        require!(pause_flags & !PAUSE_ALL == 0, ShadowLendError::InvalidPauseFlags);
+
+        let pool = &mut ctx.accounts.pool;
+        let old = pool.pause_flags;
+        pool.pause_flags = pause_flags;
+
+        emit!(PauseFlagsUpdated {
+            pool: pool.key(),
+            guardian: ctx.accounts.guardian.key(),
+            old,
+            new: pause_flags,
+        });
+
+        Ok(())
+    }
+
+    /// Assign the guardian, risk-admin and attester-manager roles
+    pub fn set_roles(
+        ctx: Context<SetRoles>,
+        guardian: Pubkey,
+        risk_admin: Pubkey,
+        attester_manager: Pubkey,
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        pool.guardian = guardian;
+        pool.risk_admin = risk_admin;
+        pool.attester_manager = attester_manager;
+
+        emit!(RolesUpdated {
+            pool: pool.key(),
+            guardian,
+            risk_admin,
+            attester_manager,
+        });
+
+        Ok(())
+    }
+
+    /// Start an authority transfer; takes effect once the new key accepts
+    pub fn propose_authority(ctx: Context<ProposeAuthority>, new_authority: Pubkey) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        pool.pending_authority = new_authority;
+
+        emit!(AuthorityProposed {
+            pool: pool.key(),
+            authority: pool.authority,
+            pending_authority: new_authority,
+        });
+
+        Ok(())
+    }
+
+    /// Complete an authority transfer, signed by the proposed key
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        let old = pool.authority;
+        pool.authority = ctx.accounts.pending_authority.key();
+        pool.pending_authority = Pubkey::default();
+
+        emit!(AuthorityTransferred {
+            pool: pool.key(),
+            old,
+            new: pool.authority,
+        });
+
+        Ok(())
+    }
+
+    /// Update the pool's risk parameters, gated by the risk admin role
+    /// Interest is accrued under the old rate model before the change applies
+    /// Changes that can hurt borrowers (see `PoolConfig::requires_timelock`)
+    /// must go through the timelock
+    pub fn update_pool_config(ctx: Context<UpdatePoolConfig>, config: PoolConfig) -> Result<()> {
+        config.validate()?;
+
+        let pool = &mut ctx.accounts.pool;
+        require!(
+            !config.requires_timelock(&pool.config()),
+            ShadowLendError::TimelockRequired
+        );
+
+        accrue_pool_interest(pool)?;
+
+        let old = pool.config();
+        pool.apply_config(&config);
+
+        emit!(PoolConfigUpdated {
+            pool: pool.key(),
+            old,
+            new: config,
+        });
+
+        Ok(())
+    }
+
+    /// Queue a config change that can be executed after CONFIG_CHANGE_DELAY
+    /// Gives borrowers time to react before risk parameters are tightened
+    pub fn queue_config_change(ctx: Context<QueueConfigChange>, config: PoolConfig) -> Result<()> {
+        config.validate()?;
+
+        let now = Clock::get()?.unix_timestamp;
+        let change = &mut ctx.accounts.config_change;
+        change.pool = ctx.accounts.pool.key();
+        change.base_config = ctx.accounts.pool.config();
+        change.config = config;
+        change.queued_at = now;
+        change.eta = now
+            .checked_add(CONFIG_CHANGE_DELAY)
+            .ok_or(ShadowLendError::MathOverflow)?;
+        change.bump = ctx.bumps.config_change;
+
+        emit!(ConfigChangeQueued {
+            pool: change.pool,
+            config,
+            eta: change.eta,
+        });
+
+        Ok(())
+    }
+
+    /// Apply a queued config change once its delay has elapsed
+    pub fn execute_config_change(ctx: Context<ExecuteConfigChange>) -> Result<()> {
+        let change = &ctx.accounts.config_change;
+        let pool = &mut ctx.accounts.pool;
+        change.check_executable(&pool.config(), Clock::get()?.unix_timestamp)?;
+        let config = change.config;
+        config.validate()?;
+        accrue_pool_interest(pool)?;
+
+        let old = pool.config();
+        pool.apply_config(&config);
+
+        emit!(PoolConfigUpdated {
+            pool: pool.key(),
+            old,
+            new: config,
+        });
+
+        Ok(())
+    }
+
+    /// Drop a queued config change without applying it
+    pub fn cancel_config_change(ctx: Context<CancelConfigChange>) -> Result<()> {
+        emit!(ConfigChangeCancelled {
+            pool: ctx.accounts.pool.key(),
+            config: ctx.accounts.config_change.config,
+        });
+
+        Ok(())
+    }
+}
+
+// ============================================================================
+// Constants
+// ============================================================================
+
+/// Fixed-point scale for the cumulative borrow index (1e18)
+pub const INDEX_SCALE: u128 = 1_000_000_000_000_000_000;
+/// Basis point denominator (10000 = 100%)
+pub const BPS_DENOMINATOR: u128 = 10_000;
+/// Seconds per year used for annual rate conversion
+pub const SECONDS_PER_YEAR: u128 = 365 * 24 * 60 * 60;
+/// Maximum number of Inco attester keys a pool can trust
+pub const MAX_ATTESTERS: usize = 5;
+/// Cap on the borrow rate at 100% utilization (basis points, 1000% APR)
+pub const MAX_BORROW_RATE: u64 = 100_000;
+/// Cap on the liquidation bonus (basis points)
+pub const MAX_LIQUIDATION_BONUS: u16 = 2_500;
+/// Cap on the share of interest diverted to the insurance fund (basis points)
+pub const MAX_INSURANCE_FACTOR: u16 = 5_000;
+/// Cap on the share of interest kept as protocol reserves (basis points)
+pub const MAX_RESERVE_FACTOR: u16 = 5_000;
+/// Cap on the borrow origination fee (basis points)
+pub const MAX_ORIGINATION_FEE: u16 = 1_000;
+/// Largest mint precision supported by the oracle price conversion
+pub const MAX_MINT_DECIMALS: u8 = 18;
+
+/// Mock price feeds are only usable in builds with the `mock-oracle` feature
+pub const MOCK_ORACLE_ENABLED: bool = cfg!(any(test, feature = "mock-oracle"));
+
+/// Minimum delay between queueing and executing a config change (2 days)
+pub const CONFIG_CHANGE_DELAY: i64 = 2 * 24 * 60 * 60;
+
+/// Pause switches stored in `Pool.pause_flags`
+pub const PAUSE_DEPOSITS: u8 = 1 << 0;      // open_position, deposit_collateral, supply
+pub const PAUSE_BORROWS: u8 = 1 << 1;       // borrow
+pub const PAUSE_WITHDRAWALS: u8 = 1 << 2;   // withdraw_collateral, redeem
+pub const PAUSE_LIQUIDATIONS: u8 = 1 << 3;  // liquidate, settle_bad_debt
+pub const PAUSE_REPAYMENTS: u8 = 1 << 4;    // repay
+pub const PAUSE_ALL: u8 = PAUSE_DEPOSITS
+    | PAUSE_BORROWS
+    | PAUSE_WITHDRAWALS
+    | PAUSE_LIQUIDATIONS
+    | PAUSE_REPAYMENTS;
+
+// ============================================================================
+// Account Structures
+// ============================================================================
+
+#[derive(Accounts)]
+#[instruction(pool_id: u32)]
+pub struct InitializePool<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Pool::INIT_SPACE,
+        seeds = [
+            b"pool",
+            collateral_mint.key().as_ref(),
+            borrow_mint.key().as_ref(),
+            &pool_id.to_le_bytes(),
+        ],
+        bump
     )]
-    pub position: Account<'info, Position>,
+    pub pool: Box<Account<'info, Pool>>,
+
+    #[account(
+        constraint = collateral_mint.decimals <= MAX_MINT_DECIMALS
+            @ ShadowLendError::UnsupportedMintDecimals
+    )]
+    pub collateral_mint: Box<Account<'info, Mint>>,
+
+    #[account(
+        constraint = borrow_mint.decimals <= MAX_MINT_DECIMALS
+            @ ShadowLendError::UnsupportedMintDecimals
+    )]
+    pub borrow_mint: Box<Account<'info, Mint>>,
+    
+    #[account(mut)]
+    pub authority: Signer<'info>,
     
-    /// CHECK: Vault PDA
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeVaults<'info> {
     #[account(
         mut,
-        seeds = [b"vault", pool.key().as_ref()],
+        seeds = [
+            b"pool",
+            pool.collateral_mint.as_ref(),
+            pool.borrow_mint.as_ref(),
+            &pool.pool_id.to_le_bytes(),
+        ],
+        bump = pool.bump,
+        has_one = authority @ ShadowLendError::Unauthorized
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    #[account(address = pool.collateral_mint @ ShadowLendError::InvalidMint)]
+    pub collateral_mint: Box<Account<'info, Mint>>,
+
+    #[account(address = pool.borrow_mint @ ShadowLendError::InvalidMint)]
+    pub borrow_mint: Box<Account<'info, Mint>>,
+
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"collateral_vault", pool.key().as_ref()],
+        bump,
+        token::mint = collateral_mint,
+        token::authority = pool
+    )]
+    pub collateral_vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"liquidity_vault", pool.key().as_ref()],
+        bump,
+        token::mint = borrow_mint,
+        token::authority = pool
+    )]
+    pub liquidity_vault: Box<Account<'info, TokenAccount>>,
+
+    /// Insurance fund, denominated in the borrow asset
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"insurance_vault", pool.key().as_ref()],
+        bump,
+        token::mint = borrow_mint,
+        token::authority = pool
+    )]
+    pub insurance_vault: Box<Account<'info, TokenAccount>>,
+
+    /// Lender share token, denominated like the borrow asset
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"share_mint", pool.key().as_ref()],
+        bump,
+        mint::decimals = borrow_mint.decimals,
+        mint::authority = pool
+    )]
+    pub share_mint: Box<Account<'info, Mint>>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+#[instruction(position_index: u32)]
+pub struct OpenPosition<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"pool",
+            pool.collateral_mint.as_ref(),
+            pool.borrow_mint.as_ref(),
+            &pool.pool_id.to_le_bytes(),
+        ],
+        bump = pool.bump
+    )]
+    pub pool: Account<'info, Pool>,
+    
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + Position::INIT_SPACE,
+        seeds = [
+            b"position",
+            pool.key().as_ref(),
+            owner.key().as_ref(),
+            &position_index.to_le_bytes(),
+        ],
         bump
     )]
-    pub vault: AccountInfo<'info>,
+    pub position: Account<'info, Position>,
     
     #[account(mut)]
-    pub liquidator: Signer<'info>,
+    pub owner: Signer<'info>,
     
     pub system_program: Program<'info, System>,
 }
 
-#[derive(Accounts)]
-pub struct ClosePosition<'info> {
-    #[account(mut)]
-    pub pool: Account<'info, Pool>,
-    
-    #[account(
-        mut,
-        seeds = [b"position", owner.key().as_ref()],
-        bump = position.bump,
-        constraint = position.owner == owner.key(),
-        close = owner
-    )]
-    pub position: Account<'info, Position>,
-    
-    #[account(mut)]
-    pub owner: Signer<'info>,
+#[derive(Accounts)]
+pub struct DepositCollateral<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"pool",
+            pool.collateral_mint.as_ref(),
+            pool.borrow_mint.as_ref(),
+            &pool.pool_id.to_le_bytes(),
+        ],
+        bump = pool.bump
+    )]
+    pub pool: Account<'info, Pool>,
+    
+    #[account(
+        mut,
+        seeds = [
+            b"position",
+            pool.key().as_ref(),
+            owner.key().as_ref(),
+            &position.index.to_le_bytes(),
+        ],
+        bump = position.bump,
+        constraint = position.owner == owner.key(),
+        constraint = position.is_active,
+        constraint = position.pool == pool.key() @ ShadowLendError::PoolMismatch
+    )]
+    pub position: Account<'info, Position>,
+    
+    /// Collateral vault owned by the pool
+    #[account(
+        mut,
+        seeds = [b"collateral_vault", pool.key().as_ref()],
+        bump
+    )]
+    pub collateral_vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        token::mint = pool.collateral_mint,
+        token::authority = owner
+    )]
+    pub owner_collateral_account: Box<Account<'info, TokenAccount>>,
+    
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    /// CHECK: Instructions sysvar, inspected for the attester's ed25519 signature
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct Borrow<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"pool",
+            pool.collateral_mint.as_ref(),
+            pool.borrow_mint.as_ref(),
+            &pool.pool_id.to_le_bytes(),
+        ],
+        bump = pool.bump
+    )]
+    pub pool: Account<'info, Pool>,
+    
+    #[account(
+        mut,
+        seeds = [
+            b"position",
+            pool.key().as_ref(),
+            owner.key().as_ref(),
+            &position.index.to_le_bytes(),
+        ],
+        bump = position.bump,
+        constraint = position.owner == owner.key(),
+        constraint = position.is_active,
+        constraint = position.pool == pool.key() @ ShadowLendError::PoolMismatch
+    )]
+    pub position: Account<'info, Position>,
+    
+    /// Lender liquidity vault owned by the pool
+    #[account(
+        mut,
+        seeds = [b"liquidity_vault", pool.key().as_ref()],
+        bump
+    )]
+    pub liquidity_vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        token::mint = pool.borrow_mint,
+        token::authority = owner
+    )]
+    pub owner_borrow_account: Box<Account<'info, TokenAccount>>,
+    
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    /// CHECK: Price feed configured on the pool, parsed by the oracle adapter
+    #[account(address = pool.oracle.price_feed @ ShadowLendError::InvalidOracle)]
+    pub oracle: UncheckedAccount<'info>,
+
+    /// CHECK: Instructions sysvar, inspected for the attester's ed25519 signature
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct Repay<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"pool",
+            pool.collateral_mint.as_ref(),
+            pool.borrow_mint.as_ref(),
+            &pool.pool_id.to_le_bytes(),
+        ],
+        bump = pool.bump
+    )]
+    pub pool: Account<'info, Pool>,
+    
+    #[account(
+        mut,
+        seeds = [
+            b"position",
+            pool.key().as_ref(),
+            owner.key().as_ref(),
+            &position.index.to_le_bytes(),
+        ],
+        bump = position.bump,
+        constraint = position.owner == owner.key(),
+        constraint = position.is_active,
+        constraint = position.pool == pool.key() @ ShadowLendError::PoolMismatch
+    )]
+    pub position: Account<'info, Position>,
+    
+    /// Lender liquidity vault owned by the pool
+    #[account(
+        mut,
+        seeds = [b"liquidity_vault", pool.key().as_ref()],
+        bump
+    )]
+    pub liquidity_vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        token::mint = pool.borrow_mint,
+        token::authority = owner
+    )]
+    pub owner_borrow_account: Box<Account<'info, TokenAccount>>,
+    
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    /// CHECK: Instructions sysvar, inspected for the attester's ed25519 signature
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawCollateral<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"pool",
+            pool.collateral_mint.as_ref(),
+            pool.borrow_mint.as_ref(),
+            &pool.pool_id.to_le_bytes(),
+        ],
+        bump = pool.bump
+    )]
+    pub pool: Account<'info, Pool>,
+    
+    #[account(
+        mut,
+        seeds = [
+            b"position",
+            pool.key().as_ref(),
+            owner.key().as_ref(),
+            &position.index.to_le_bytes(),
+        ],
+        bump = position.bump,
+        constraint = position.owner == owner.key(),
+        constraint = position.is_active,
+        constraint = position.pool == pool.key() @ ShadowLendError::PoolMismatch
+    )]
+    pub position: Account<'info, Position>,
+    
+    /// Collateral vault owned by the pool
+    #[account(
+        mut,
+        seeds = [b"collateral_vault", pool.key().as_ref()],
+        bump
+    )]
+    pub collateral_vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        token::mint = pool.collateral_mint,
+        token::authority = owner
+    )]
+    pub owner_collateral_account: Box<Account<'info, TokenAccount>>,
+    
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    
+    /// CHECK: Price feed configured on the pool, parsed by the oracle adapter
+    #[account(address = pool.oracle.price_feed @ ShadowLendError::InvalidOracle)]
+    pub oracle: UncheckedAccount<'info>,
+
+    /// CHECK: Instructions sysvar, inspected for the attester's ed25519 signature
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct Liquidate<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"pool",
+            pool.collateral_mint.as_ref(),
+            pool.borrow_mint.as_ref(),
+            &pool.pool_id.to_le_bytes(),
+        ],
+        bump = pool.bump
+    )]
+    pub pool: Account<'info, Pool>,
+    
+    #[account(
+        mut,
+        seeds = [
+            b"position",
+            pool.key().as_ref(),
+            position.owner.as_ref(),
+            &position.index.to_le_bytes(),
+        ],
+        bump = position.bump,
+        constraint = position.is_active,
+        constraint = position.pool == pool.key() @ ShadowLendError::PoolMismatch
+    )]
+    pub position: Account<'info, Position>,
+    
+    /// Collateral vault owned by the pool
+    #[account(
+        mut,
+        seeds = [b"collateral_vault", pool.key().as_ref()],
+        bump
+    )]
+    pub collateral_vault: Box<Account<'info, TokenAccount>>,
+
+    /// Lender liquidity vault owned by the pool
+    #[account(
+        mut,
+        seeds = [b"liquidity_vault", pool.key().as_ref()],
+        bump
+    )]
+    pub liquidity_vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        token::mint = pool.borrow_mint,
+        token::authority = liquidator
+    )]
+    pub liquidator_borrow_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        token::mint = pool.collateral_mint,
+        token::authority = liquidator
+    )]
+    pub liquidator_collateral_account: Box<Account<'info, TokenAccount>>,
+    
+    #[account(mut)]
+    pub liquidator: Signer<'info>,
+    
+    /// CHECK: Price feed configured on the pool, parsed by the oracle adapter
+    #[account(address = pool.oracle.price_feed @ ShadowLendError::InvalidOracle)]
+    pub oracle: UncheckedAccount<'info>,
+
+    /// CHECK: Instructions sysvar, inspected for the attester's ed25519 signature
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct SettleBadDebt<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"pool",
+            pool.collateral_mint.as_ref(),
+            pool.borrow_mint.as_ref(),
+            &pool.pool_id.to_le_bytes(),
+        ],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"position",
+            pool.key().as_ref(),
+            position.owner.as_ref(),
+            &position.index.to_le_bytes(),
+        ],
+        bump = position.bump,
+        constraint = position.is_active,
+        constraint = position.pool == pool.key() @ ShadowLendError::PoolMismatch
+    )]
+    pub position: Account<'info, Position>,
+
+    /// Lender liquidity vault owned by the pool
+    #[account(
+        mut,
+        seeds = [b"liquidity_vault", pool.key().as_ref()],
+        bump
+    )]
+    pub liquidity_vault: Box<Account<'info, TokenAccount>>,
+
+    /// Insurance fund owned by the pool
+    #[account(
+        mut,
+        seeds = [b"insurance_vault", pool.key().as_ref()],
+        bump
+    )]
+    pub insurance_vault: Box<Account<'info, TokenAccount>>,
+
+    /// Collateral vault owned by the pool
+    #[account(
+        mut,
+        seeds = [b"collateral_vault", pool.key().as_ref()],
+        bump
+    )]
+    pub collateral_vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        token::mint = pool.borrow_mint,
+        token::authority = keeper
+    )]
+    pub keeper_borrow_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        token::mint = pool.collateral_mint,
+        token::authority = keeper
+    )]
+    pub keeper_collateral_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+
+    /// CHECK: Price feed configured on the pool, parsed by the oracle adapter
+    #[account(address = pool.oracle.price_feed @ ShadowLendError::InvalidOracle)]
+    pub oracle: UncheckedAccount<'info>,
+
+    /// CHECK: Instructions sysvar, inspected for the attester's ed25519 signature
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ClosePosition<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"pool",
+            pool.collateral_mint.as_ref(),
+            pool.borrow_mint.as_ref(),
+            &pool.pool_id.to_le_bytes(),
+        ],
+        bump = pool.bump
+    )]
+    pub pool: Account<'info, Pool>,
+    
+    #[account(
+        mut,
+        seeds = [
+            b"position",
+            pool.key().as_ref(),
+            owner.key().as_ref(),
+            &position.index.to_le_bytes(),
+        ],
+        bump = position.bump,
+        constraint = position.owner == owner.key(),
+        constraint = position.pool == pool.key() @ ShadowLendError::PoolMismatch,
+        close = owner
+    )]
+    pub position: Account<'info, Position>,
+    
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// CHECK: Instructions sysvar, inspected for the attester's ed25519 signature
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Supply<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"pool",
+            pool.collateral_mint.as_ref(),
+            pool.borrow_mint.as_ref(),
+            &pool.pool_id.to_le_bytes(),
+        ],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Lender liquidity vault owned by the pool
+    #[account(
+        mut,
+        seeds = [b"liquidity_vault", pool.key().as_ref()],
+        bump
+    )]
+    pub liquidity_vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut, address = pool.share_mint)]
+    pub share_mint: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        token::mint = pool.borrow_mint,
+        token::authority = supplier
+    )]
+    pub supplier_borrow_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        token::mint = share_mint,
+        token::authority = supplier
+    )]
+    pub supplier_share_account: Box<Account<'info, TokenAccount>>,
+
+    pub supplier: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct Redeem<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"pool",
+            pool.collateral_mint.as_ref(),
+            pool.borrow_mint.as_ref(),
+            &pool.pool_id.to_le_bytes(),
+        ],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Lender liquidity vault owned by the pool
+    #[account(
+        mut,
+        seeds = [b"liquidity_vault", pool.key().as_ref()],
+        bump
+    )]
+    pub liquidity_vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut, address = pool.share_mint)]
+    pub share_mint: Box<Account<'info, Mint>>,
+
+    #[account(
+        mut,
+        token::mint = pool.borrow_mint,
+        token::authority = supplier
+    )]
+    pub supplier_borrow_account: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        token::mint = share_mint,
+        token::authority = supplier
+    )]
+    pub supplier_share_account: Box<Account<'info, TokenAccount>>,
+
+    pub supplier: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct AccrueInterest<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"pool",
+            pool.collateral_mint.as_ref(),
+            pool.borrow_mint.as_ref(),
+            &pool.pool_id.to_le_bytes(),
+        ],
+        bump = pool.bump
+    )]
+    pub pool: Account<'info, Pool>,
+}
+
+#[derive(Accounts)]
+pub struct SweepInsurance<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"pool",
+            pool.collateral_mint.as_ref(),
+            pool.borrow_mint.as_ref(),
+            &pool.pool_id.to_le_bytes(),
+        ],
+        bump = pool.bump
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Lender liquidity vault owned by the pool
+    #[account(
+        mut,
+        seeds = [b"liquidity_vault", pool.key().as_ref()],
+        bump
+    )]
+    pub liquidity_vault: Box<Account<'info, TokenAccount>>,
+
+    /// Insurance fund owned by the pool
+    #[account(
+        mut,
+        seeds = [b"insurance_vault", pool.key().as_ref()],
+        bump
+    )]
+    pub insurance_vault: Box<Account<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct SetTreasury<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"pool",
+            pool.collateral_mint.as_ref(),
+            pool.borrow_mint.as_ref(),
+            &pool.pool_id.to_le_bytes(),
+        ],
+        bump = pool.bump,
+        has_one = authority @ ShadowLendError::Unauthorized
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    #[account(constraint = treasury.mint == pool.borrow_mint @ ShadowLendError::InvalidMint)]
+    pub treasury: Box<Account<'info, TokenAccount>>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawReserves<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"pool",
+            pool.collateral_mint.as_ref(),
+            pool.borrow_mint.as_ref(),
+            &pool.pool_id.to_le_bytes(),
+        ],
+        bump = pool.bump,
+        has_one = authority @ ShadowLendError::Unauthorized,
+        has_one = treasury @ ShadowLendError::InvalidTreasury
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    /// Lender liquidity vault owned by the pool
+    #[account(
+        mut,
+        seeds = [b"liquidity_vault", pool.key().as_ref()],
+        bump
+    )]
+    pub liquidity_vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub treasury: Box<Account<'info, TokenAccount>>,
+
+    pub authority: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeMockPriceFeed<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + MockPriceFeed::INIT_SPACE
+    )]
+    pub price_feed: Account<'info, MockPriceFeed>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetMockPrice<'info> {
+    #[account(mut, has_one = authority @ ShadowLendError::Unauthorized)]
+    pub price_feed: Account<'info, MockPriceFeed>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetAttesters<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"pool",
+            pool.collateral_mint.as_ref(),
+            pool.borrow_mint.as_ref(),
+            &pool.pool_id.to_le_bytes(),
+        ],
+        bump = pool.bump,
+        has_one = attester_manager @ ShadowLendError::Unauthorized
+    )]
+    pub pool: Account<'info, Pool>,
+
+    pub attester_manager: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetPauseFlags<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"pool",
+            pool.collateral_mint.as_ref(),
+            pool.borrow_mint.as_ref(),
+            &pool.pool_id.to_le_bytes(),
+        ],
+        bump = pool.bump,
+        has_one = guardian @ ShadowLendError::Unauthorized
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    pub guardian: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetRoles<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"pool",
+            pool.collateral_mint.as_ref(),
+            pool.borrow_mint.as_ref(),
+            &pool.pool_id.to_le_bytes(),
+        ],
+        bump = pool.bump,
+        has_one = authority @ ShadowLendError::Unauthorized
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"pool",
+            pool.collateral_mint.as_ref(),
+            pool.borrow_mint.as_ref(),
+            &pool.pool_id.to_le_bytes(),
+        ],
+        bump = pool.bump,
+        has_one = authority @ ShadowLendError::Unauthorized
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"pool",
+            pool.collateral_mint.as_ref(),
+            pool.borrow_mint.as_ref(),
+            &pool.pool_id.to_le_bytes(),
+        ],
+        bump = pool.bump,
+        constraint = pool.pending_authority == pending_authority.key() @ ShadowLendError::Unauthorized
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    pub pending_authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePoolConfig<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"pool",
+            pool.collateral_mint.as_ref(),
+            pool.borrow_mint.as_ref(),
+            &pool.pool_id.to_le_bytes(),
+        ],
+        bump = pool.bump,
+        has_one = risk_admin @ ShadowLendError::Unauthorized
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    pub risk_admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct QueueConfigChange<'info> {
+    #[account(
+        seeds = [
+            b"pool",
+            pool.collateral_mint.as_ref(),
+            pool.borrow_mint.as_ref(),
+            &pool.pool_id.to_le_bytes(),
+        ],
+        bump = pool.bump,
+        has_one = risk_admin @ ShadowLendError::Unauthorized
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    #[account(
+        init,
+        payer = risk_admin,
+        space = 8 + PendingConfigChange::INIT_SPACE,
+        seeds = [b"config_change", pool.key().as_ref()],
+        bump
+    )]
+    pub config_change: Account<'info, PendingConfigChange>,
+
+    #[account(mut)]
+    pub risk_admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteConfigChange<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"pool",
+            pool.collateral_mint.as_ref(),
+            pool.borrow_mint.as_ref(),
+            &pool.pool_id.to_le_bytes(),
+        ],
+        bump = pool.bump,
+        has_one = risk_admin @ ShadowLendError::Unauthorized
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    #[account(
+        mut,
+        seeds = [b"config_change", pool.key().as_ref()],
+        bump = config_change.bump,
+        close = risk_admin
+    )]
+    pub config_change: Account<'info, PendingConfigChange>,
+
+    #[account(mut)]
+    pub risk_admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelConfigChange<'info> {
+    #[account(
+        seeds = [
+            b"pool",
+            pool.collateral_mint.as_ref(),
+            pool.borrow_mint.as_ref(),
+            &pool.pool_id.to_le_bytes(),
+        ],
+        bump = pool.bump,
+        has_one = risk_admin @ ShadowLendError::Unauthorized
+    )]
+    pub pool: Box<Account<'info, Pool>>,
+
+    #[account(
+        mut,
+        seeds = [b"config_change", pool.key().as_ref()],
+        bump = config_change.bump,
+        close = risk_admin
+    )]
+    pub config_change: Account<'info, PendingConfigChange>,
+
+    #[account(mut)]
+    pub risk_admin: Signer<'info>,
+}
+
+// ============================================================================
+// State Accounts
+// ============================================================================
+
+/// Pool: Public lending pool state
+#[account]
+#[derive(InitSpace)]
+pub struct Pool {
+    pub authority: Pubkey,              // 32
+    pub collateral_mint: Pubkey,        // 32
+    pub borrow_mint: Pubkey,            // 32
+    pub pool_id: u32,                   // 4 (distinguishes pools for the same pair)
+    pub share_mint: Pubkey,             // 32 (lender share token)
+    pub ltv_ratio: u16,                 // 2 (basis points)
+    pub rate_model: InterestRateModel,  // 8 (basis points)
+    pub liquidation_threshold: u16,     // 2
+    pub liquidation_config: LiquidationConfig, // 4 (basis points)
+    pub insurance_factor: u16,          // 2 (share of interest for insurance, basis points)
+    pub pending_insurance: u64,         // 8 (insurance share not yet swept into the fund)
+    pub reserve_factor: u16,            // 2 (share of interest for the protocol, basis points)
+    pub origination_fee: u16,           // 2 (charged on borrow and added to debt, basis points)
+    pub protocol_reserves: u64,         // 8 (protocol share held in the liquidity vault)
+    pub treasury: Pubkey,               // 32 (borrow-mint account receiving reserves)
+    pub oracle: OracleConfig,           // 43 (collateral price feed and guards)
+    pub collateral_decimals: u8,        // 1
+    pub borrow_decimals: u8,            // 1
+    pub total_collateral: u64,          // 8 (collateral reserve, public aggregate)
+    pub total_deposits: u64,            // 8 (lender liquidity, public aggregate)
+    pub total_borrows: u64,             // 8 (public aggregate, includes accrued interest)
+    pub borrow_index: u128,             // 16 (cumulative, scaled by INDEX_SCALE)
+    pub interest_remainder: u128,       // 16 (sub-unit interest carried over, scaled by INDEX_SCALE)
+    pub last_accrual_ts: i64,           // 8
+    pub active_positions: u64,          // 8
+    pub attesters: [Pubkey; MAX_ATTESTERS], // 160 (trusted Inco signers)
+    pub attester_count: u8,             // 1
+    pub pending_authority: Pubkey,      // 32 (proposed authority, default if none)
+    pub guardian: Pubkey,               // 32 (controls pause_flags)
+    pub risk_admin: Pubkey,             // 32 (controls risk parameters)
+    pub attester_manager: Pubkey,       // 32 (controls attester set)
+    pub pause_flags: u8,                // 1 (PAUSE_* bits)
+    pub bump: u8,                       // 1
+}
+
+impl Pool {
+    /// Whether `key` is one of the pool's configured attesters
+    pub fn is_attester(&self, key: &Pubkey) -> bool {
+        self.attesters[..self.attester_count as usize].contains(key)
+    }
+
+    /// Fail if the operation guarded by `flag` is paused
+    pub fn require_not_paused(&self, flag: u8) -> Result<()> {
+        require!(self.pause_flags & flag == 0, ShadowLendError::OperationPaused);
+        Ok(())
+    }
+
+    /// Current risk configuration
+    pub fn config(&self) -> PoolConfig {
+        PoolConfig {
+            ltv_ratio: self.ltv_ratio,
+            liquidation_threshold: self.liquidation_threshold,
+            rate_model: self.rate_model,
+            liquidation_config: self.liquidation_config,
+            insurance_factor: self.insurance_factor,
+            reserve_factor: self.reserve_factor,
+            origination_fee: self.origination_fee,
+            oracle: self.oracle,
+        }
+    }
+
+    /// Replace the risk configuration; `config` must already be validated
+    pub fn apply_config(&mut self, config: &PoolConfig) {
+        self.ltv_ratio = config.ltv_ratio;
+        self.liquidation_threshold = config.liquidation_threshold;
+        self.rate_model = config.rate_model;
+        self.liquidation_config = config.liquidation_config;
+        self.insurance_factor = config.insurance_factor;
+        self.reserve_factor = config.reserve_factor;
+        self.origination_fee = config.origination_fee;
+        self.oracle = config.oracle;
+    }
+
+    /// Origination fee owed on a borrow of `amount`, rounded up
+    pub fn origination_fee_for(&self, amount: u64) -> Result<u64> {
+        let fee = (amount as u128 * self.origination_fee as u128)
+            .div_ceil(BPS_DENOMINATOR);
+        u64::try_from(fee).map_err(|_| error!(ShadowLendError::MathOverflow))
+    }
+
+    /// Idle liquidity in the liquidity vault
+    /// Pool assets (idle liquidity + borrows) are owed to suppliers
+    /// (total_deposits), the insurance fund (pending_insurance) and the
+    /// protocol (protocol_reserves)
+    pub fn available_liquidity(&self) -> u64 {
+        self.total_deposits
+            .saturating_add(self.pending_insurance)
+            .saturating_add(self.protocol_reserves)
+            .saturating_sub(self.total_borrows)
+    }
+
+    /// Share of lender liquidity currently borrowed (basis points)
+    pub fn utilization(&self) -> u64 {
+        if self.total_deposits == 0 {
+            return 0;
+        }
+        let utilization = (self.total_borrows as u128) * BPS_DENOMINATOR
+            / (self.total_deposits as u128);
+        utilization.min(BPS_DENOMINATOR) as u64
+    }
+
+    /// Compound interest on outstanding borrows up to `now`
+    /// Interest grows total_borrows; the insurance and reserve factors' shares are
+    /// set aside for the insurance fund and protocol reserves, and the rest grows
+    /// total_deposits, so it flows to lenders through the share exchange rate.
+    /// Returns the interest accrued.
+    pub fn accrue_interest(&mut self, now: i64) -> Result<u64> {
+        let elapsed = now.saturating_sub(self.last_accrual_ts);
+        if elapsed <= 0 {
+            return Ok(0);
+        }
+        self.last_accrual_ts = now;
+
+        // Per-period rate scaled by INDEX_SCALE: annual_bps * elapsed / (10000 * year)
+        let borrow_rate = self.rate_model.borrow_rate(self.utilization());
+        let factor = (borrow_rate as u128)
+            .checked_mul(elapsed as u128)
+            .and_then(|v| v.checked_mul(INDEX_SCALE))
+            .map(|v| v / (BPS_DENOMINATOR * SECONDS_PER_YEAR))
+            .ok_or(ShadowLendError::MathOverflow)?;
+        if factor == 0 {
+            return Ok(0);
+        }
+
+        let index_delta = self
+            .borrow_index
+            .checked_mul(factor)
+            .map(|v| v / INDEX_SCALE)
+            .ok_or(ShadowLendError::MathOverflow)?;
+        self.borrow_index = self
+            .borrow_index
+            .checked_add(index_delta)
+            .ok_or(ShadowLendError::MathOverflow)?;
+
+        // Carry the sub-unit remainder so frequent accruals on small pools
+        // still credit interest and total_borrows keeps pace with the index
+        let scaled_interest = (self.total_borrows as u128)
+            .checked_mul(factor)
+            .and_then(|v| v.checked_add(self.interest_remainder))
+            .ok_or(ShadowLendError::MathOverflow)?;
+        self.interest_remainder = scaled_interest % INDEX_SCALE;
+        let interest = u64::try_from(scaled_interest / INDEX_SCALE)
+            .map_err(|_| error!(ShadowLendError::MathOverflow))?;
+        self.total_borrows = self
+            .total_borrows
+            .checked_add(interest)
+            .ok_or(ShadowLendError::MathOverflow)?;
+
+        let insurance_share = (interest as u128 * self.insurance_factor as u128
+            / BPS_DENOMINATOR) as u64;
+        self.pending_insurance = self
+            .pending_insurance
+            .checked_add(insurance_share)
+            .ok_or(ShadowLendError::MathOverflow)?;
+        let reserve_share = (interest as u128 * self.reserve_factor as u128
+            / BPS_DENOMINATOR) as u64;
+        self.protocol_reserves = self
+            .protocol_reserves
+            .checked_add(reserve_share)
+            .ok_or(ShadowLendError::MathOverflow)?;
+        self.total_deposits = self
+            .total_deposits
+            .checked_add(interest - insurance_share - reserve_share)
+            .ok_or(ShadowLendError::MathOverflow)?;
+
+        Ok(interest)
+    }
+
+    /// Current value of a debt written at `debt_index`, rounded up
+    /// This is the old debt every attestation is made against
+    pub fn accrued_debt(&self, debt: u64, debt_index: u128) -> Option<u64> {
+        if debt_index == 0 {
+            return None;
+        }
+        let accrued = (debt as u128)
+            .checked_mul(self.borrow_index)?
+            .div_ceil(debt_index);
+        u64::try_from(accrued).ok()
+    }
+
+    /// Collateral released to a liquidator repaying `repay_amount`
+    /// Converts the repaid value plus bonus into collateral base units at `price`
+    pub fn liquidation_seize_amount(&self, repay_amount: u64, price: &OraclePrice) -> Option<u64> {
+        let multiplier = BPS_DENOMINATOR + self.liquidation_config.liquidation_bonus as u128;
+
+        // seize = repay * multiplier * 10^(collateral_decimals - borrow_decimals - expo)
+        //         / (price * BPS_DENOMINATOR)
+        // Net the powers of ten and divide once at the end, so neither the
+        // bonus nor the decimal scaling is truncated early
+        let exponent = self.collateral_decimals as i32 - self.borrow_decimals as i32 - price.expo;
+        let scale = 10u128.checked_pow(exponent.unsigned_abs())?;
+        let mut numerator = (repay_amount as u128).checked_mul(multiplier)?;
+        let mut denominator = u128::try_from(price.price)
+            .ok()?
+            .checked_mul(BPS_DENOMINATOR)?;
+        if exponent >= 0 {
+            numerator = numerator.checked_mul(scale)?;
+        } else {
+            denominator = denominator.checked_mul(scale)?;
+        }
+        if denominator == 0 {
+            return None;
+        }
+
+        u64::try_from(numerator / denominator).ok()
+    }
+
+    /// Value of `collateral` in borrow base units at `price`, rounded up
+    /// Rounding favours the pool when a keeper buys residual collateral
+    pub fn collateral_value(&self, collateral: u64, price: &OraclePrice) -> Option<u64> {
+        // value = collateral * price * 10^(borrow_decimals - collateral_decimals + expo)
+        let exponent = self.borrow_decimals as i32 - self.collateral_decimals as i32 + price.expo;
+        let scale = 10u128.checked_pow(exponent.unsigned_abs())?;
+        let value = (collateral as u128).checked_mul(u128::try_from(price.price).ok()?)?;
+        let value = if exponent >= 0 {
+            value.checked_mul(scale)?
+        } else {
+            value.div_ceil(scale)
+        };
+        u64::try_from(value).ok()
+    }
+
+    /// Shares minted for supplying `amount`
+    /// Exchange rate = total_deposits / share_supply, where total_deposits
+    /// covers idle liquidity plus outstanding borrows and their interest.
+    /// None when existing shares are backed by nothing (e.g. after bad debt
+    /// wiped out total_deposits), since new supply would be diluted into them
+    pub fn shares_for_amount(&self, amount: u64, share_supply: u64) -> Option<u64> {
+        if share_supply == 0 {
+            return Some(amount);
+        }
+        if self.total_deposits == 0 {
+            return None;
+        }
+        let shares = (amount as u128)
+            .checked_mul(share_supply as u128)?
+            .checked_div(self.total_deposits as u128)?;
+        u64::try_from(shares).ok()
+    }
+
+    /// Underlying liquidity returned for burning `shares`
+    pub fn amount_for_shares(&self, shares: u64, share_supply: u64) -> Option<u64> {
+        if share_supply == 0 {
+            return None;
+        }
+        let amount = (shares as u128)
+            .checked_mul(self.total_deposits as u128)?
+            .checked_div(share_supply as u128)?;
+        u64::try_from(amount).ok()
+    }
+}
+
+/// Risk parameters an authority can change after initialization
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub struct PoolConfig {
+    pub ltv_ratio: u16,
+    pub liquidation_threshold: u16,
+    pub rate_model: InterestRateModel,
+    pub liquidation_config: LiquidationConfig,
+    pub insurance_factor: u16,
+    pub reserve_factor: u16,
+    pub origination_fee: u16,
+    pub oracle: OracleConfig,
+}
+
+impl PoolConfig {
+    /// Whether moving from `current` to this config can hurt existing
+    /// borrowers and therefore has to wait out CONFIG_CHANGE_DELAY: a lower
+    /// ltv or liquidation threshold, a larger close factor or liquidation
+    /// bonus, a different price source, or looser staleness or confidence
+    /// guards on it
+    pub fn requires_timelock(&self, current: &PoolConfig) -> bool {
+        self.ltv_ratio < current.ltv_ratio
+            || self.liquidation_threshold < current.liquidation_threshold
+            || self.liquidation_config.close_factor > current.liquidation_config.close_factor
+            || self.liquidation_config.liquidation_bonus
+                > current.liquidation_config.liquidation_bonus
+            || self.oracle.source != current.oracle.source
+            || self.oracle.price_feed != current.oracle.price_feed
+            || self.oracle.max_price_age_slots > current.oracle.max_price_age_slots
+            || self.oracle.max_confidence > current.oracle.max_confidence
+    }
+
+    /// Check basis-point invariants:
+    /// 0 < ltv < liquidation_threshold <= 10000, rates nonzero and capped
+    pub fn validate(&self) -> Result<()> {
+        self.validate_for_build(MOCK_ORACLE_ENABLED)
+    }
+
+    /// `validate` with the mock oracle allowed or not, independent of the build
+    fn validate_for_build(&self, mock_oracle_enabled: bool) -> Result<()> {
+        let bps = BPS_DENOMINATOR as u16;
+
+        require!(
+            self.ltv_ratio > 0 && self.ltv_ratio < self.liquidation_threshold,
+            ShadowLendError::InvalidLtvRatio
+        );
+        require!(
+            self.liquidation_threshold <= bps,
+            ShadowLendError::InvalidLiquidationThreshold
+        );
+
+        let model = &self.rate_model;
+        require!(
+            model.slope_low > 0
+                && model.slope_high > 0
+                && model.optimal_utilization > 0
+                && model.optimal_utilization < bps
+                && model.borrow_rate(BPS_DENOMINATOR as u64) <= MAX_BORROW_RATE,
+            ShadowLendError::InvalidInterestRateModel
+        );
+
+        // A liquidation must leave the position healthier than before:
+        // threshold * (1 + bonus) has to stay below 100%
+        let liquidation = &self.liquidation_config;
+        require!(
+            liquidation.close_factor > 0
+                && liquidation.close_factor <= bps
+                && liquidation.liquidation_bonus <= MAX_LIQUIDATION_BONUS
+                && (self.liquidation_threshold as u128)
+                    * (BPS_DENOMINATOR + liquidation.liquidation_bonus as u128)
+                    < BPS_DENOMINATOR * BPS_DENOMINATOR,
+            ShadowLendError::InvalidLiquidationConfig
+        );
+
+        require!(
+            self.insurance_factor <= MAX_INSURANCE_FACTOR,
+            ShadowLendError::InvalidInsuranceFactor
+        );
+        require!(
+            self.reserve_factor <= MAX_RESERVE_FACTOR,
+            ShadowLendError::InvalidReserveFactor
+        );
+        require!(
+            self.origination_fee <= MAX_ORIGINATION_FEE,
+            ShadowLendError::InvalidOriginationFee
+        );
+
+        require!(
+            mock_oracle_enabled || self.oracle.source != OracleSource::Mock,
+            ShadowLendError::InvalidOracleConfig
+        );
+        require!(
+            self.oracle.max_price_age_slots > 0
+                && self.oracle.max_confidence > 0
+                && self.oracle.max_confidence <= bps,
+            ShadowLendError::InvalidOracleConfig
+        );
+
+        Ok(())
+    }
+}
+
+/// Kinked interest rate model (all values in basis points)
+/// Below optimal utilization the rate climbs along `slope_low`; above it,
+/// along the much steeper `slope_high` to pull liquidity back into the pool
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub struct InterestRateModel {
+    pub base_rate: u16,                 // Annual rate at 0% utilization
+    pub slope_low: u16,                 // Added rate from 0% to optimal utilization
+    pub slope_high: u16,                // Added rate from optimal to 100% utilization
+    pub optimal_utilization: u16,       // Kink point
+}
+
+impl InterestRateModel {
+    /// Annual borrow rate (basis points) at `utilization` (basis points)
+    pub fn borrow_rate(&self, utilization: u64) -> u64 {
+        let utilization = utilization.min(BPS_DENOMINATOR as u64);
+        let optimal = self.optimal_utilization as u64;
+        let base = self.base_rate as u64;
+        let slope_low = self.slope_low as u64;
+        let slope_high = self.slope_high as u64;
+
+        if utilization <= optimal {
+            if optimal == 0 {
+                return base;
+            }
+            base + slope_low * utilization / optimal
+        } else {
+            let excess = utilization - optimal;
+            let range = BPS_DENOMINATOR as u64 - optimal;
+            base + slope_low + slope_high * excess / range
+        }
+    }
+}
+
+/// Partial liquidation parameters (basis points)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub struct LiquidationConfig {
+    pub close_factor: u16,              // Max share of debt repaid per liquidation
+    pub liquidation_bonus: u16,         // Extra collateral paid to the liquidator
 }
 
-// ============================================================================
-// State Accounts
-// ============================================================================
+/// Price feed flavours understood by the oracle adapter
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum OracleSource {
+    /// Pyth v2 price account
+    Pyth,
+    /// `MockPriceFeed` account owned by this program (localnet and tests)
+    /// Only accepted when built with the `mock-oracle` feature
+    Mock,
+}
 
-/// Pool: Public lending pool state
+/// Collateral price feed, quoting one whole collateral token in whole
+/// borrow-asset tokens
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub struct OracleConfig {
+    pub source: OracleSource,
+    pub price_feed: Pubkey,
+    pub max_price_age_slots: u64,       // Reject prices published longer ago than this
+    pub max_confidence: u16,            // Max confidence interval, basis points of price
+}
+
+/// Mock price feed for localnet and tests, mirroring the Pyth price fields
 #[account]
 #[derive(InitSpace)]
-pub struct Pool {
-    pub authority: Pubkey,              // 32
-    pub collateral_mint: Pubkey,        // 32
-    pub borrow_mint: Pubkey,            // 32
-    pub ltv_ratio: u16,                 // 2 (basis points)
-    pub interest_rate: u16,             // 2 (basis points)
-    pub liquidation_threshold: u16,     // 2
-    pub total_deposits: u64,            // 8 (public aggregate)
-    pub total_borrows: u64,             // 8 (public aggregate)
-    pub active_positions: u64,          // 8
-    pub bump: u8,                       // 1
-    pub is_active: bool,                // 1
+pub struct MockPriceFeed {
+    pub authority: Pubkey,                  // 32
+    pub price: i64,                         // 8
+    pub conf: u64,                          // 8
+    pub expo: i32,                          // 4
+    pub publish_slot: u64,                  // 8
+}
+
+/// Config change queued behind the timelock, one per pool
+#[account]
+#[derive(InitSpace)]
+pub struct PendingConfigChange {
+    pub pool: Pubkey,                       // 32
+    pub base_config: PoolConfig,            // Pool config when queued; must be unchanged on execution
+    pub config: PoolConfig,                 // Parameters applied on execution
+    pub queued_at: i64,                     // 8
+    pub eta: i64,                           // 8 (earliest execution time)
+    pub bump: u8,                           // 1
+}
+
+impl PendingConfigChange {
+    /// Whether the change may be applied to a pool whose config is `current`
+    pub fn check_executable(&self, current: &PoolConfig, now: i64) -> Result<()> {
+        require!(now >= self.eta, ShadowLendError::TimelockNotElapsed);
+
+        // Applying the full snapshot would silently revert direct updates
+        // made while the change was queued
+        require!(
+            *current == self.base_config,
+            ShadowLendError::ConfigChangedSinceQueued
+        );
+
+        Ok(())
+    }
 }
 
 /// Position: User's private lending position
@@ -546,10 +2533,13 @@ pub struct Pool {
 pub struct Position {
     pub owner: Pubkey,                      // 32
     pub pool: Pubkey,                       // 32
+    pub index: u32,                         // 4 (per-owner position index)
     #[max_len(32)]
     pub encrypted_collateral: [u8; 32],     // Inco-encrypted collateral amount
     #[max_len(32)]
     pub encrypted_debt: [u8; 32],           // Inco-encrypted debt amount
+    pub debt_index: u128,                   // 16 (pool borrow_index the debt was last written at)
+    pub nonce: u64,                         // 8 (consumed by each attested transition)
     pub last_update: i64,                   // 8
     pub is_active: bool,                    // 1
     pub bump: u8,                           // 1
@@ -564,8 +2554,17 @@ pub struct PoolInitialized {
     pub pool: Pubkey,
     pub collateral_mint: Pubkey,
     pub borrow_mint: Pubkey,
-    pub ltv_ratio: u16,
-    pub interest_rate: u16,
+    pub pool_id: u32,
+    pub config: PoolConfig,
+}
+
+#[event]
+pub struct VaultsInitialized {
+    pub pool: Pubkey,
+    pub collateral_vault: Pubkey,
+    pub liquidity_vault: Pubkey,
+    pub insurance_vault: Pubkey,
+    pub share_mint: Pubkey,
 }
 
 #[event]
@@ -573,6 +2572,7 @@ pub struct PositionOpened {
     pub position: Pubkey,
     pub owner: Pubkey,
     pub pool: Pubkey,
+    pub index: u32,
 }
 
 #[event]
@@ -588,6 +2588,7 @@ pub struct Borrowed {
     pub position: Pubkey,
     pub owner: Pubkey,
     pub amount: u64,
+    pub fee: u64,
     pub timestamp: i64,
 }
 
@@ -612,211 +2613,1772 @@ pub struct PositionLiquidated {
     pub position: Pubkey,
     pub owner: Pubkey,
     pub liquidator: Pubkey,
+    pub repay_amount: u64,
+    pub seize_amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BadDebtSettled {
+    pub pool: Pubkey,
+    pub position: Pubkey,
+    pub keeper: Pubkey,
+    pub shortfall: u64,
+    pub residual_collateral: u64,
+    pub residual_value: u64,
+    pub covered: u64,
+    pub socialized: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PositionClosed {
+    pub position: Pubkey,
+    pub owner: Pubkey,
+}
+
+#[event]
+pub struct Supplied {
+    pub pool: Pubkey,
+    pub supplier: Pubkey,
+    pub amount: u64,
+    pub shares: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct Redeemed {
+    pub pool: Pubkey,
+    pub supplier: Pubkey,
+    pub amount: u64,
+    pub shares: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AttestersUpdated {
+    pub pool: Pubkey,
+    pub attesters: Vec<Pubkey>,
+}
+
+#[event]
+pub struct PauseFlagsUpdated {
+    pub pool: Pubkey,
+    pub guardian: Pubkey,
+    pub old: u8,
+    pub new: u8,
+}
+
+#[event]
+pub struct RolesUpdated {
+    pub pool: Pubkey,
+    pub guardian: Pubkey,
+    pub risk_admin: Pubkey,
+    pub attester_manager: Pubkey,
+}
+
+#[event]
+pub struct AuthorityProposed {
+    pub pool: Pubkey,
+    pub authority: Pubkey,
+    pub pending_authority: Pubkey,
+}
+
+#[event]
+pub struct AuthorityTransferred {
+    pub pool: Pubkey,
+    pub old: Pubkey,
+    pub new: Pubkey,
+}
+
+#[event]
+pub struct PoolConfigUpdated {
+    pub pool: Pubkey,
+    pub old: PoolConfig,
+    pub new: PoolConfig,
+}
+
+#[event]
+pub struct ConfigChangeQueued {
+    pub pool: Pubkey,
+    pub config: PoolConfig,
+    pub eta: i64,
+}
+
+#[event]
+pub struct ConfigChangeCancelled {
+    pub pool: Pubkey,
+    pub config: PoolConfig,
+}
+
+#[event]
+pub struct InterestAccrued {
+    pub pool: Pubkey,
+    pub interest: u64,
+    pub borrow_index: u128,
+    pub total_borrows: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct InsuranceFunded {
+    pub pool: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ReservesAccrued {
+    pub pool: Pubkey,
+    pub amount: u64,
+    pub protocol_reserves: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ReservesWithdrawn {
+    pub pool: Pubkey,
+    pub treasury: Pubkey,
+    pub amount: u64,
+    pub protocol_reserves: u64,
     pub timestamp: i64,
 }
 
-#[event]
-pub struct PositionClosed {
-    pub position: Pubkey,
-    pub owner: Pubkey,
-}
+#[event]
+pub struct TreasuryUpdated {
+    pub pool: Pubkey,
+    pub treasury: Pubkey,
+}
+
+// ============================================================================
+// Errors
+// ============================================================================
+
+#[error_code]
+pub enum ShadowLendError {
+    #[msg("Invalid amount: must be greater than 0")]
+    InvalidAmount,
+    #[msg("Invalid Inco proof")]
+    InvalidIncoProof,
+    #[msg("Insufficient liquidity in pool")]
+    InsufficientLiquidity,
+    #[msg("Insufficient collateral in pool")]
+    InsufficientCollateral,
+    #[msg("Position is healthy, cannot liquidate")]
+    PositionHealthy,
+    #[msg("Position has outstanding debt")]
+    PositionHasDebt,
+    #[msg("Unauthorized")]
+    Unauthorized,
+    #[msg("Operation is paused")]
+    OperationPaused,
+    #[msg("Unknown pause flag")]
+    InvalidPauseFlags,
+    #[msg("Mint does not belong to this pool")]
+    InvalidMint,
+    #[msg("Mint decimals are not supported")]
+    UnsupportedMintDecimals,
+    #[msg("Position does not belong to this pool")]
+    PoolMismatch,
+    #[msg("Too many attesters")]
+    TooManyAttesters,
+    #[msg("Oracle account does not match the pool's price feed")]
+    InvalidOracle,
+    #[msg("Mock oracle is not enabled in this build")]
+    MockOracleDisabled,
+    #[msg("Oracle price is invalid or unavailable")]
+    InvalidOraclePrice,
+    #[msg("Oracle price is stale")]
+    StalePrice,
+    #[msg("Oracle price confidence interval is too wide")]
+    PriceConfidenceTooWide,
+    #[msg("LTV ratio must be nonzero and below the liquidation threshold")]
+    InvalidLtvRatio,
+    #[msg("Liquidation threshold must not exceed 100%")]
+    InvalidLiquidationThreshold,
+    #[msg("Interest rate model is out of bounds")]
+    InvalidInterestRateModel,
+    #[msg("Close factor or liquidation bonus is out of bounds")]
+    InvalidLiquidationConfig,
+    #[msg("Insurance factor is out of bounds")]
+    InvalidInsuranceFactor,
+    #[msg("Reserve factor exceeds maximum")]
+    InvalidReserveFactor,
+    #[msg("Origination fee exceeds maximum")]
+    InvalidOriginationFee,
+    #[msg("Amount exceeds protocol reserves")]
+    InsufficientReserves,
+    #[msg("Treasury account does not match pool configuration")]
+    InvalidTreasury,
+    #[msg("Oracle staleness or confidence limit is out of bounds")]
+    InvalidOracleConfig,
+    #[msg("This config change must be queued behind the timelock")]
+    TimelockRequired,
+    #[msg("Config change delay has not elapsed")]
+    TimelockNotElapsed,
+    #[msg("Pool config changed since the config change was queued")]
+    ConfigChangedSinceQueued,
+    #[msg("Outstanding shares have no backing deposits")]
+    UnbackedShares,
+    #[msg("Residual collateral covers the debt, liquidate instead")]
+    PositionNotInsolvent,
+    #[msg("Bad debt exceeds total deposits")]
+    BadDebtExceedsDeposits,
+    #[msg("Math overflow")]
+    MathOverflow,
+}
+
+// ============================================================================
+// Interest Accrual
+// ============================================================================
+
+/// Accrue pool interest up to the current clock, logging the reserve share
+fn accrue_pool_interest(pool: &mut Account<Pool>) -> Result<u64> {
+    let now = Clock::get()?.unix_timestamp;
+    let reserves_before = pool.protocol_reserves;
+    let interest = pool.accrue_interest(now)?;
+
+    let accrued = pool.protocol_reserves - reserves_before;
+    if accrued > 0 {
+        emit!(ReservesAccrued {
+            pool: pool.key(),
+            amount: accrued,
+            protocol_reserves: pool.protocol_reserves,
+            timestamp: now,
+        });
+    }
+
+    Ok(interest)
+}
+
+// ============================================================================
+// Token Transfers
+// ============================================================================
+
+/// Move tokens from a user-owned account into a pool vault
+fn transfer_to_vault<'info>(
+    token_program: &Program<'info, Token>,
+    from: &Account<'info, TokenAccount>,
+    vault: &Account<'info, TokenAccount>,
+    authority: &Signer<'info>,
+    amount: u64,
+) -> Result<()> {
+    token::transfer(
+        CpiContext::new(
+            token_program.to_account_info(),
+            Transfer {
+                from: from.to_account_info(),
+                to: vault.to_account_info(),
+                authority: authority.to_account_info(),
+            },
+        ),
+        amount,
+    )
+}
+
+/// Move tokens out of a pool vault, signed by the pool PDA
+fn transfer_from_vault<'info>(
+    token_program: &Program<'info, Token>,
+    pool: &Account<'info, Pool>,
+    vault: &Account<'info, TokenAccount>,
+    to: &Account<'info, TokenAccount>,
+    amount: u64,
+) -> Result<()> {
+    let pool_id = pool.pool_id.to_le_bytes();
+    let signer_seeds: &[&[&[u8]]] = &[&[
+        b"pool",
+        pool.collateral_mint.as_ref(),
+        pool.borrow_mint.as_ref(),
+        &pool_id,
+        &[pool.bump],
+    ]];
+
+    token::transfer(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            Transfer {
+                from: vault.to_account_info(),
+                to: to.to_account_info(),
+                authority: pool.to_account_info(),
+            },
+            signer_seeds,
+        ),
+        amount,
+    )
+}
+
+// ============================================================================
+// Oracle Adapter
+// ============================================================================
+//
+// Prices are normalized into a Pyth-style (price, conf, expo, publish_slot)
+// tuple regardless of the feed behind them. The tuple is committed to in the
+// attested health computation, so the attester and the program must agree on
+// the exact price used.
+
+// Pyth v2 price account layout
+const PYTH_MAGIC: u32 = 0xa1b2_c3d4;
+const PYTH_VERSION: u32 = 2;
+const PYTH_PRICE_ACCOUNT_TYPE: u32 = 3;
+const PYTH_STATUS_TRADING: u32 = 1;
+const PYTH_EXPO_OFFSET: usize = 20;
+const PYTH_AGG_PRICE_OFFSET: usize = 208;
+const PYTH_AGG_CONF_OFFSET: usize = 216;
+const PYTH_AGG_STATUS_OFFSET: usize = 224;
+const PYTH_AGG_PUBLISH_SLOT_OFFSET: usize = 232;
+const PYTH_PRICE_ACCOUNT_MIN_LEN: usize = 240;
+
+/// Normalized oracle reading
+#[derive(Clone, Copy, Debug)]
+pub struct OraclePrice {
+    pub price: i64,
+    pub conf: u64,
+    pub expo: i32,
+    pub publish_slot: u64,
+}
+
+impl OraclePrice {
+    /// Bytes committed to in proof digests
+    fn to_digest_bytes(self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(28);
+        bytes.extend_from_slice(&self.price.to_le_bytes());
+        bytes.extend_from_slice(&self.conf.to_le_bytes());
+        bytes.extend_from_slice(&self.expo.to_le_bytes());
+        bytes.extend_from_slice(&self.publish_slot.to_le_bytes());
+        bytes
+    }
+}
+
+impl OracleSource {
+    /// Read the current price from `account` using this source's layout
+    pub fn load_price(&self, account: &AccountInfo) -> Result<OraclePrice> {
+        let price = match self {
+            OracleSource::Pyth => parse_pyth_price(&account.try_borrow_data()?),
+            OracleSource::Mock => {
+                require!(MOCK_ORACLE_ENABLED, ShadowLendError::MockOracleDisabled);
+                require_keys_eq!(*account.owner, crate::ID, ShadowLendError::InvalidOracle);
+                let feed = MockPriceFeed::try_deserialize(&mut &account.try_borrow_data()?[..])?;
+                Some(OraclePrice {
+                    price: feed.price,
+                    conf: feed.conf,
+                    expo: feed.expo,
+                    publish_slot: feed.publish_slot,
+                })
+            }
+        };
+
+        match price {
+            Some(price) if price.price > 0 => Ok(price),
+            _ => err!(ShadowLendError::InvalidOraclePrice),
+        }
+    }
+}
+
+impl OracleConfig {
+    /// Read the feed's current price, refusing stale or low-confidence prices
+    pub fn load_price(&self, account: &AccountInfo) -> Result<OraclePrice> {
+        let price = self.source.load_price(account)?;
+
+        let current_slot = Clock::get()?.slot;
+        let age = current_slot.saturating_sub(price.publish_slot);
+        if age > self.max_price_age_slots {
+            msg!("Oracle price is {} slots old (max {})", age, self.max_price_age_slots);
+            return err!(ShadowLendError::StalePrice);
+        }
+
+        // conf / price > max_confidence / 10000
+        let conf_bps = (price.conf as u128) * BPS_DENOMINATOR;
+        let max_conf_bps = (price.price as u128) * (self.max_confidence as u128);
+        if conf_bps > max_conf_bps {
+            msg!("Oracle confidence {} too wide for price {}", price.conf, price.price);
+            return err!(ShadowLendError::PriceConfidenceTooWide);
+        }
+
+        Ok(price)
+    }
+}
+
+/// Read a little-endian integer field from a Pyth account
+fn read_le<const N: usize>(data: &[u8], offset: usize) -> Option<[u8; N]> {
+    data.get(offset..offset + N)?.try_into().ok()
+}
+
+/// Parse the aggregate price of a Pyth v2 price account
+/// Returns None unless the account is a price account that is trading
+fn parse_pyth_price(data: &[u8]) -> Option<OraclePrice> {
+    if data.len() < PYTH_PRICE_ACCOUNT_MIN_LEN {
+        return None;
+    }
+
+    let magic = u32::from_le_bytes(read_le(data, 0)?);
+    let version = u32::from_le_bytes(read_le(data, 4)?);
+    let account_type = u32::from_le_bytes(read_le(data, 8)?);
+    if magic != PYTH_MAGIC || version != PYTH_VERSION || account_type != PYTH_PRICE_ACCOUNT_TYPE {
+        msg!("Not a Pyth price account");
+        return None;
+    }
+
+    let status = u32::from_le_bytes(read_le(data, PYTH_AGG_STATUS_OFFSET)?);
+    if status != PYTH_STATUS_TRADING {
+        msg!("Pyth price is not trading: status={}", status);
+        return None;
+    }
+
+    Some(OraclePrice {
+        price: i64::from_le_bytes(read_le(data, PYTH_AGG_PRICE_OFFSET)?),
+        conf: u64::from_le_bytes(read_le(data, PYTH_AGG_CONF_OFFSET)?),
+        expo: i32::from_le_bytes(read_le(data, PYTH_EXPO_OFFSET)?),
+        publish_slot: u64::from_le_bytes(read_le(data, PYTH_AGG_PUBLISH_SLOT_OFFSET)?),
+    })
+}
+
+// ============================================================================
+// Inco Proof Verification
+// ============================================================================
+//
+// Proof Format (version 6, 28 bytes):
+// - Byte 0:      Format version (PROOF_VERSION)
+// - Byte 1:      Proof kind (ProofKind)
+// - Bytes 2-9:   Position nonce (u64 little-endian)
+// - Bytes 10-17: Expiry slot (u64 little-endian)
+// - Bytes 18-25: Amount (u64 little-endian)
+// - Bytes 26-27: Risk parameter the health check used (u16 little-endian):
+//                LTV ratio for borrow/withdraw, liquidation threshold for
+//                liquidation. Must equal the pool's current configuration.
+//
+// Borrow, withdrawal, liquidation and bad debt digests also commit to the oracle price
+// (price, conf, expo, publish slot) the health computation used, so the
+// attested LTV check is made in borrow-asset units at a price the program
+// itself read. Borrow digests additionally commit to the pool's origination
+// fee and the fee charged, so the attested new debt is old debt + amount + fee.
+// Liquidation digests additionally commit to the pool's close factor and
+// liquidation bonus and to the collateral amount being seized.
+//
+// Deposit, bad debt and repay proofs carry no risk parameter (bytes 26-27
+// are zero). A deposit proof attests new collateral = old collateral + amount.
+// A bad debt proof's digest also commits to the residual collateral the
+// keeper buys at that price.
+// A repay proof's amount is the applied amount min(requested, debt); its
+// digest also commits to the requested amount, and it attests new debt =
+// old debt - applied. Close proofs carry neither an amount nor a risk
+// parameter; they attest that the position's current collateral and debt
+// ciphertexts both decrypt to zero.
+//
+// The attester does not sign these bytes directly. It signs a 32-byte digest
+// (see `compute_proof_digest`) over the header plus everything the program
+// knows on its own: the pool, the position and its owner, the full current
+// ciphertexts and the new ciphertexts the instruction is about to write. A
+// valid proof therefore cannot be paired with a different new ciphertext,
+// position or instruction.
+//
+// Debt accrues interest through the pool's borrow index. The encrypted debt is
+// denominated at `position.debt_index`, so the debt an attestation treats as
+// the position's current ("old") debt is
+//     old_debt * pool.borrow_index / position.debt_index   (rounded up)
+// (see `Pool::accrued_debt`). Every digest commits to both indices, and each
+// transition that writes a new debt ciphertext moves `debt_index` to the
+// current borrow index.
+//
+// The nonce must equal `position.nonce`, which is bumped after every attested
+// transition, so each attestation authorizes exactly one state change and
+// cannot be replayed once the position returns to the same ciphertext.
+//
+// The proof is only accepted when the same transaction carries an ed25519
+// program instruction in which one of the pool's attesters signed the digest,
+// i.e. Inco attested that the computation (e.g. collateral * LTV >= debt +
+// amount) is valid. The ed25519 precompile checks the signature itself; we
+// introspect the instructions sysvar to confirm who signed what.
+
+const PROOF_VERSION: u8 = 6;
+const PROOF_LENGTH: usize = 28;
+const PROOF_DOMAIN: &[u8] = b"shadowlend:inco-proof";
+
+// ed25519 program instruction layout
+const ED25519_OFFSETS_START: usize = 2;
+const ED25519_PUBKEY_SIZE: usize = 32;
+const ED25519_SIGNATURE_SIZE: usize = 64;
+
+/// Read a little-endian u16 from the ed25519 offsets table
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+/// Extract (signer, message) from a single-signature ed25519 instruction
+/// Only self-contained instructions are accepted: the signature, pubkey and
+/// message must all live in the ed25519 instruction's own data
+fn parse_ed25519_instruction(data: &[u8]) -> Option<(Pubkey, &[u8])> {
+    if data.first() != Some(&1) {
+        return None;
+    }
+
+    let offsets = ED25519_OFFSETS_START;
+    let signature_offset = read_u16(data, offsets)? as usize;
+    let signature_ix = read_u16(data, offsets + 2)?;
+    let pubkey_offset = read_u16(data, offsets + 4)? as usize;
+    let pubkey_ix = read_u16(data, offsets + 6)?;
+    let message_offset = read_u16(data, offsets + 8)? as usize;
+    let message_size = read_u16(data, offsets + 10)? as usize;
+    let message_ix = read_u16(data, offsets + 12)?;
+
+    if signature_ix != u16::MAX || pubkey_ix != u16::MAX || message_ix != u16::MAX {
+        return None;
+    }
+    if data.len() < signature_offset + ED25519_SIGNATURE_SIZE {
+        return None;
+    }
+
+    let pubkey_bytes: [u8; 32] = data
+        .get(pubkey_offset..pubkey_offset + ED25519_PUBKEY_SIZE)?
+        .try_into()
+        .ok()?;
+    let message = data.get(message_offset..message_offset + message_size)?;
+
+    Some((Pubkey::new_from_array(pubkey_bytes), message))
+}
+
+/// Check that an earlier instruction in this transaction is an ed25519
+/// signature by one of the pool's attesters over exactly `message`
+fn verify_attester_signature(
+    instructions: &AccountInfo,
+    pool: &Pool,
+    message: &[u8],
+) -> bool {
+    let current_index = match sysvar_instructions::load_current_index_checked(instructions) {
+        Ok(index) => index as usize,
+        Err(_) => {
+            msg!("Instructions sysvar unavailable");
+            return false;
+        }
+    };
+
+    for index in 0..current_index {
+        let ix = match sysvar_instructions::load_instruction_at_checked(index, instructions) {
+            Ok(ix) => ix,
+            Err(_) => continue,
+        };
+        if ix.program_id != ed25519_program::ID {
+            continue;
+        }
+
+        if let Some((signer, signed_message)) = parse_ed25519_instruction(&ix.data) {
+            if signed_message == message && pool.is_attester(&signer) {
+                return true;
+            }
+        }
+    }
+
+    msg!("No attester signature found for proof");
+    false
+}
+
+/// State transition an attestation authorizes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+enum ProofKind {
+    Borrow = 1,
+    Withdraw = 2,
+    Liquidate = 3,
+    BadDebt = 4,
+    Close = 5,
+    Repay = 6,
+    Deposit = 7,
+}
+
+/// Parsed proof header
+struct ProofHeader {
+    version: u8,
+    kind: u8,
+    nonce: u64,
+    expiry_slot: u64,
+    amount: u64,
+    param: u16,
+}
+
+impl ProofHeader {
+    fn parse(proof: &[u8]) -> Option<Self> {
+        if proof.len() != PROOF_LENGTH {
+            msg!("Invalid proof length: {} != {}", proof.len(), PROOF_LENGTH);
+            return None;
+        }
+
+        Some(Self {
+            version: proof[0],
+            kind: proof[1],
+            nonce: u64::from_le_bytes(proof[2..10].try_into().ok()?),
+            expiry_slot: u64::from_le_bytes(proof[10..18].try_into().ok()?),
+            amount: u64::from_le_bytes(proof[18..26].try_into().ok()?),
+            param: u16::from_le_bytes(proof[26..28].try_into().ok()?),
+        })
+    }
+
+    /// Check the header against the requested transition, the position's
+    /// current nonce and the current slot
+    fn authorizes(&self, transition: &Transition, nonce: u64, current_slot: u64) -> bool {
+        if self.version != PROOF_VERSION {
+            msg!("Unsupported proof version: {}", self.version);
+            return false;
+        }
+
+        if self.kind != transition.kind as u8 {
+            msg!("Proof kind mismatch: proof={} vs expected={:?}", self.kind, transition.kind);
+            return false;
+        }
+
+        if self.nonce != nonce {
+            msg!("Stale proof nonce: proof={} vs position={}", self.nonce, nonce);
+            return false;
+        }
 
-// ============================================================================
-// Errors
-// ============================================================================
+        if current_slot > self.expiry_slot {
+            msg!("Proof expired: slot {} > expiry {}", current_slot, self.expiry_slot);
+            return false;
+        }
 
-#[error_code]
-pub enum ShadowLendError {
-    #[msg("Invalid amount: must be greater than 0")]
-    InvalidAmount,
-    #[msg("Invalid Inco proof")]
-    InvalidIncoProof,
-    #[msg("Insufficient liquidity in pool")]
-    InsufficientLiquidity,
-    #[msg("Position is healthy, cannot liquidate")]
-    PositionHealthy,
-    #[msg("Position has outstanding debt")]
-    PositionHasDebt,
-    #[msg("Unauthorized")]
-    Unauthorized,
+        if self.amount != transition.amount {
+            msg!("Amount mismatch: proof={} vs requested={}", self.amount, transition.amount);
+            return false;
+        }
+
+        if self.param != transition.param {
+            msg!(
+                "Attested risk parameter {} does not match pool configuration {}",
+                self.param,
+                transition.param
+            );
+            return false;
+        }
+
+        true
+    }
 }
 
-// ============================================================================
-// Inco Proof Verification
-// ============================================================================
-//
-// Proof Format (64 bytes):
-// - Bytes 0-15:  First 16 bytes of encrypted_collateral (binding)
-// - Bytes 16-31: First 16 bytes of encrypted_debt (binding)
-// - Bytes 32-39: Amount (u64 little-endian)
-// - Bytes 40-41: LTV ratio or threshold (u16 little-endian)
-// - Bytes 42-57: Proof hash/signature
-// - Bytes 58-63: Reserved
-//
-// The proof binds to the position's encrypted state and the requested amount.
-// In production, bytes 42-57 would contain an Inco MPC signature verifying
-// that the computation (collateral * LTV >= debt + amount) is valid.
-
-const PROOF_MIN_LENGTH: usize = 64;
-const PROOF_HASH_OFFSET: usize = 42;
-const PROOF_HASH_LENGTH: usize = 16;
-
-/// Compute a simple binding hash for proof verification
-/// In production: This would be replaced with Inco MPC signature verification
-fn compute_proof_binding(
-    encrypted_collateral: &[u8; 32],
-    encrypted_debt: &[u8; 32],
+/// Encrypted state transition an instruction requests an attestation for
+#[derive(Clone)]
+struct Transition<'a> {
+    kind: ProofKind,
     amount: u64,
     param: u16,
-) -> [u8; 16] {
-    use anchor_lang::solana_program::hash::hash;
+    new_collateral: &'a [u8; 32],
+    new_debt: &'a [u8; 32],
+    price: Option<OraclePrice>,         // Oracle price the health check used
+    terms: Vec<u8>,                     // Kind-specific terms covered by the digest
+}
 
-    // Create binding data: collateral + debt + amount + param
-    let mut data = Vec::with_capacity(32 + 32 + 8 + 2);
-    data.extend_from_slice(encrypted_collateral);
-    data.extend_from_slice(encrypted_debt);
-    data.extend_from_slice(&amount.to_le_bytes());
-    data.extend_from_slice(&param.to_le_bytes());
+/// Digest the attester signs for a given header and transition
+fn compute_proof_digest(
+    header: &ProofHeader,
+    pool_key: &Pubkey,
+    pool: &Pool,
+    position_key: &Pubkey,
+    position: &Position,
+    transition: &Transition,
+) -> [u8; 32] {
+    let price = transition
+        .price
+        .map(OraclePrice::to_digest_bytes)
+        .unwrap_or_default();
 
-    // Hash and take first 16 bytes
-    let hash_result = hash(&data);
-    let mut result = [0u8; 16];
-    result.copy_from_slice(&hash_result.to_bytes()[..16]);
-    result
+    hashv(&[
+        PROOF_DOMAIN,
+        &[header.version, header.kind],
+        pool_key.as_ref(),
+        position_key.as_ref(),
+        position.owner.as_ref(),
+        &header.nonce.to_le_bytes(),
+        &header.expiry_slot.to_le_bytes(),
+        &header.amount.to_le_bytes(),
+        &header.param.to_le_bytes(),
+        &pool.borrow_index.to_le_bytes(),
+        &position.debt_index.to_le_bytes(),
+        &position.encrypted_collateral,
+        &position.encrypted_debt,
+        transition.new_collateral,
+        transition.new_debt,
+        &price,
+        &transition.terms,
+    ])
+    .to_bytes()
 }
 
-/// Verify Inco proof for borrow operation
-/// Validates that: collateral * LTV >= debt + amount
-fn verify_inco_borrow_proof(
+/// Verify an Inco proof for `transition` on `position`
+fn verify_inco_proof(
     proof: &[u8],
-    position: &Position,
-    amount: u64,
+    transition: &Transition,
+    pool: &Account<Pool>,
+    position: &Account<Position>,
+    instructions: &AccountInfo,
 ) -> bool {
-    // Check minimum proof length
-    if proof.len() < PROOF_MIN_LENGTH {
-        msg!("Proof too short: {} < {}", proof.len(), PROOF_MIN_LENGTH);
-        return false;
-    }
+    let header = match ProofHeader::parse(proof) {
+        Some(header) => header,
+        None => return false,
+    };
 
-    // Verify collateral binding (first 16 bytes must match position)
-    if proof[0..16] != position.encrypted_collateral[0..16] {
-        msg!("Collateral binding mismatch");
+    let current_slot = match Clock::get() {
+        Ok(clock) => clock.slot,
+        Err(_) => return false,
+    };
+    if !header.authorizes(transition, position.nonce, current_slot) {
         return false;
     }
 
-    // Verify debt binding (bytes 16-31 must match position)
-    if proof[16..32] != position.encrypted_debt[0..16] {
-        msg!("Debt binding mismatch");
+    let digest = compute_proof_digest(
+        &header,
+        &pool.key(),
+        pool,
+        &position.key(),
+        position,
+        transition,
+    );
+    verify_attester_signature(instructions, pool, &digest)
+}
+
+/// Verify Inco proof for collateral deposit
+/// Validates that `encrypted_new_collateral` encrypts collateral + amount
+fn verify_inco_deposit_proof(
+    proof: &[u8],
+    pool: &Account<Pool>,
+    position: &Account<Position>,
+    amount: u64,
+    encrypted_new_collateral: &[u8; 32],
+    instructions: &AccountInfo,
+) -> bool {
+    let transition = Transition {
+        kind: ProofKind::Deposit,
+        amount,
+        param: 0,
+        new_collateral: encrypted_new_collateral,
+        new_debt: &position.encrypted_debt,
+        price: None,
+        terms: Vec::new(),
+    };
+    if !verify_inco_proof(proof, &transition, pool, position, instructions) {
         return false;
     }
 
-    // Verify amount matches (bytes 32-39)
-    let proof_amount = u64::from_le_bytes(proof[32..40].try_into().unwrap_or([0; 8]));
-    if proof_amount != amount {
-        msg!("Amount mismatch: proof={} vs requested={}", proof_amount, amount);
+    msg!("Deposit proof verified: amount={}", amount);
+    true
+}
+
+/// Verify Inco proof for borrow operation
+/// Validates that: collateral * LTV >= debt + amount + fee, and that
+/// `encrypted_new_debt` encrypts debt + amount + fee
+#[allow(clippy::too_many_arguments)]
+fn verify_inco_borrow_proof(
+    proof: &[u8],
+    pool: &Account<Pool>,
+    position: &Account<Position>,
+    amount: u64,
+    fee: u64,
+    encrypted_new_debt: &[u8; 32],
+    price: &OraclePrice,
+    instructions: &AccountInfo,
+) -> bool {
+    let mut terms = Vec::with_capacity(10);
+    terms.extend_from_slice(&pool.origination_fee.to_le_bytes());
+    terms.extend_from_slice(&fee.to_le_bytes());
+
+    let transition = Transition {
+        kind: ProofKind::Borrow,
+        amount,
+        param: pool.ltv_ratio,
+        new_collateral: &position.encrypted_collateral,
+        new_debt: encrypted_new_debt,
+        price: Some(*price),
+        terms,
+    };
+    if !verify_inco_proof(proof, &transition, pool, position, instructions) {
         return false;
     }
 
-    // Extract LTV ratio from proof (bytes 40-41)
-    let ltv_ratio = u16::from_le_bytes(proof[40..42].try_into().unwrap_or([0; 2]));
-
-    // Verify proof hash/signature (bytes 42-57)
-    // In production: This would verify an Inco MPC signature
-    // For demo: We verify a deterministic hash binding
-    let expected_hash = compute_proof_binding(
-        &position.encrypted_collateral,
-        &position.encrypted_debt,
+    msg!(
+        "Borrow proof verified: amount={}, fee={}, ltv={}",
         amount,
-        ltv_ratio,
+        fee,
+        pool.ltv_ratio
     );
+    true
+}
 
-    let proof_hash: [u8; 16] = proof[PROOF_HASH_OFFSET..PROOF_HASH_OFFSET + PROOF_HASH_LENGTH]
-        .try_into()
-        .unwrap_or([0; 16]);
-
-    // For hackathon demo: Accept if proof structure is valid
-    // In production: Verify expected_hash == proof_hash after MPC signing
-    if proof_hash == [0u8; 16] {
-        // Empty hash means demo mode - just verify structure
-        msg!("Demo mode: Accepting structurally valid borrow proof");
-        return true;
-    }
-
-    if proof_hash != expected_hash {
-        msg!("Proof hash mismatch");
+/// Verify Inco proof for repayment
+/// Validates that applied = min(amount, debt) and that `encrypted_new_debt`
+/// encrypts debt - applied
+fn verify_inco_repay_proof(
+    proof: &[u8],
+    pool: &Account<Pool>,
+    position: &Account<Position>,
+    amount: u64,
+    applied_amount: u64,
+    encrypted_new_debt: &[u8; 32],
+    instructions: &AccountInfo,
+) -> bool {
+    let transition = Transition {
+        kind: ProofKind::Repay,
+        amount: applied_amount,
+        param: 0,
+        new_collateral: &position.encrypted_collateral,
+        new_debt: encrypted_new_debt,
+        price: None,
+        terms: amount.to_le_bytes().to_vec(),
+    };
+    if !verify_inco_proof(proof, &transition, pool, position, instructions) {
         return false;
     }
 
-    msg!("Borrow proof verified: amount={}, ltv={}", amount, ltv_ratio);
+    msg!(
+        "Repay proof verified: amount={}, applied={}",
+        amount,
+        applied_amount
+    );
     true
 }
 
 /// Verify Inco proof for withdrawal operation
-/// Validates that: (collateral - amount) * LTV >= debt
+/// Validates that: (collateral - amount) * LTV >= debt, and that
+/// `encrypted_new_collateral` encrypts collateral - amount
 fn verify_inco_withdrawal_proof(
     proof: &[u8],
-    position: &Position,
+    pool: &Account<Pool>,
+    position: &Account<Position>,
     amount: u64,
+    encrypted_new_collateral: &[u8; 32],
+    price: &OraclePrice,
+    instructions: &AccountInfo,
 ) -> bool {
-    // Check minimum proof length
-    if proof.len() < PROOF_MIN_LENGTH {
-        msg!("Proof too short: {} < {}", proof.len(), PROOF_MIN_LENGTH);
+    let transition = Transition {
+        kind: ProofKind::Withdraw,
+        amount,
+        param: pool.ltv_ratio,
+        new_collateral: encrypted_new_collateral,
+        new_debt: &position.encrypted_debt,
+        price: Some(*price),
+        terms: Vec::new(),
+    };
+    if !verify_inco_proof(proof, &transition, pool, position, instructions) {
         return false;
     }
 
-    // Verify collateral binding
-    if proof[0..16] != position.encrypted_collateral[0..16] {
-        msg!("Collateral binding mismatch");
-        return false;
-    }
+    msg!("Withdrawal proof verified: amount={}", amount);
+    true
+}
+
+/// Verify Inco proof for liquidation
+/// Validates that: health factor < threshold, repay_amount <= debt * close
+/// factor, and that the new ciphertexts encrypt debt - repay_amount and
+/// collateral - seize_amount
+#[allow(clippy::too_many_arguments)]
+fn verify_inco_liquidation_proof(
+    proof: &[u8],
+    pool: &Account<Pool>,
+    position: &Account<Position>,
+    repay_amount: u64,
+    seize_amount: u64,
+    encrypted_new_collateral: &[u8; 32],
+    encrypted_new_debt: &[u8; 32],
+    price: &OraclePrice,
+    instructions: &AccountInfo,
+) -> bool {
+    let config = &pool.liquidation_config;
+    let mut terms = Vec::with_capacity(12);
+    terms.extend_from_slice(&config.close_factor.to_le_bytes());
+    terms.extend_from_slice(&config.liquidation_bonus.to_le_bytes());
+    terms.extend_from_slice(&seize_amount.to_le_bytes());
 
-    // Verify debt binding
-    if proof[16..32] != position.encrypted_debt[0..16] {
-        msg!("Debt binding mismatch");
+    let transition = Transition {
+        kind: ProofKind::Liquidate,
+        amount: repay_amount,
+        param: pool.liquidation_threshold,
+        new_collateral: encrypted_new_collateral,
+        new_debt: encrypted_new_debt,
+        price: Some(*price),
+        terms,
+    };
+    if !verify_inco_proof(proof, &transition, pool, position, instructions) {
         return false;
     }
 
-    // Verify amount matches
-    let proof_amount = u64::from_le_bytes(proof[32..40].try_into().unwrap_or([0; 8]));
-    if proof_amount != amount {
-        msg!("Amount mismatch: proof={} vs requested={}", proof_amount, amount);
+    msg!(
+        "Liquidation proof verified: repay={}, seize={}, threshold={}",
+        repay_amount,
+        seize_amount,
+        pool.liquidation_threshold
+    );
+    true
+}
+
+/// Verify Inco proof for bad debt settlement
+/// Validates that: collateral == residual_collateral, debt == shortfall, and
+/// that `encrypted_new_collateral` and `encrypted_new_debt` encrypt zero,
+/// against the same oracle price the residual is valued at
+#[allow(clippy::too_many_arguments)]
+fn verify_inco_bad_debt_proof(
+    proof: &[u8],
+    pool: &Account<Pool>,
+    position: &Account<Position>,
+    shortfall: u64,
+    residual_collateral: u64,
+    encrypted_new_collateral: &[u8; 32],
+    encrypted_new_debt: &[u8; 32],
+    price: &OraclePrice,
+    instructions: &AccountInfo,
+) -> bool {
+    let transition = Transition {
+        kind: ProofKind::BadDebt,
+        amount: shortfall,
+        param: 0,
+        new_collateral: encrypted_new_collateral,
+        new_debt: encrypted_new_debt,
+        price: Some(*price),
+        terms: residual_collateral.to_le_bytes().to_vec(),
+    };
+    if !verify_inco_proof(proof, &transition, pool, position, instructions) {
         return false;
     }
 
-    // For demo mode: Accept if structure is valid
-    // In production: Would verify Inco MPC signature proving remaining collateral is sufficient
-    msg!("Withdrawal proof verified: amount={}", amount);
+    msg!(
+        "Bad debt proof verified: shortfall={}, residual_collateral={}",
+        shortfall,
+        residual_collateral
+    );
     true
 }
 
-/// Verify Inco proof for liquidation (health factor < threshold)
-fn verify_inco_liquidation_proof(
+/// Verify Inco proof for closing a position
+/// Validates that both encrypted collateral and encrypted debt are zero
+fn verify_inco_close_proof(
     proof: &[u8],
-    position: &Position,
+    pool: &Account<Pool>,
+    position: &Account<Position>,
+    instructions: &AccountInfo,
 ) -> bool {
-    // Check minimum proof length
-    if proof.len() < PROOF_MIN_LENGTH {
-        msg!("Proof too short");
+    let transition = Transition {
+        kind: ProofKind::Close,
+        amount: 0,
+        param: 0,
+        new_collateral: &position.encrypted_collateral,
+        new_debt: &position.encrypted_debt,
+        price: None,
+        terms: Vec::new(),
+    };
+    if !verify_inco_proof(proof, &transition, pool, position, instructions) {
         return false;
     }
 
-    // Verify collateral binding
-    if proof[0..16] != position.encrypted_collateral[0..16] {
-        msg!("Collateral binding mismatch");
-        return false;
+    msg!("Close proof verified: position={}", position.key());
+    true
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ed25519 instruction data layout used by the tests:
+    // header (2) | offsets (14) | pubkey (32) | signature (64) | message
+    const TEST_PUBKEY_OFFSET: u16 = 16;
+    const TEST_SIGNATURE_OFFSET: u16 = 48;
+    const TEST_MESSAGE_OFFSET: u16 = 112;
+
+    fn ed25519_ix_data(signer: &Pubkey, message: &[u8]) -> Vec<u8> {
+        let mut data = vec![1u8, 0];
+        for value in [
+            TEST_SIGNATURE_OFFSET,
+            u16::MAX,
+            TEST_PUBKEY_OFFSET,
+            u16::MAX,
+            TEST_MESSAGE_OFFSET,
+            message.len() as u16,
+            u16::MAX,
+        ] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.extend_from_slice(signer.as_ref());
+        data.extend_from_slice(&[7u8; 64]);
+        data.extend_from_slice(message);
+        data
     }
 
-    // Verify debt binding
-    if proof[16..32] != position.encrypted_debt[0..16] {
-        msg!("Debt binding mismatch");
-        return false;
+    fn set_u16(data: &mut [u8], offset: usize, value: u16) {
+        data[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
     }
 
-    // Extract liquidation threshold from proof (bytes 32-33)
-    let threshold = u16::from_le_bytes(proof[32..34].try_into().unwrap_or([0; 2]));
+    #[test]
+    fn ed25519_parses_self_contained_instruction() {
+        let signer = Pubkey::new_unique();
+        let data = ed25519_ix_data(&signer, b"digest");
 
-    // For demo mode: Accept if structure is valid
-    // In production: Would verify Inco MPC signature proving health < threshold
-    msg!("Liquidation proof verified: threshold={}", threshold);
-    true
+        let (parsed_signer, message) = parse_ed25519_instruction(&data).unwrap();
+        assert_eq!(parsed_signer, signer);
+        assert_eq!(message, b"digest");
+    }
+
+    #[test]
+    fn ed25519_rejects_multiple_signatures() {
+        let mut data = ed25519_ix_data(&Pubkey::new_unique(), b"digest");
+        data[0] = 2;
+        assert!(parse_ed25519_instruction(&data).is_none());
+    }
+
+    #[test]
+    fn ed25519_rejects_data_from_other_instructions() {
+        // signature, pubkey and message instruction indices
+        for offset in [4, 8, 14] {
+            let mut data = ed25519_ix_data(&Pubkey::new_unique(), b"digest");
+            set_u16(&mut data, offset, 0);
+            assert!(parse_ed25519_instruction(&data).is_none());
+        }
+    }
+
+    #[test]
+    fn ed25519_rejects_out_of_range_offsets() {
+        let mut data = ed25519_ix_data(&Pubkey::new_unique(), b"digest");
+        set_u16(&mut data, 12, 200);
+        assert!(parse_ed25519_instruction(&data).is_none());
+
+        let mut data = ed25519_ix_data(&Pubkey::new_unique(), b"digest");
+        let len = data.len() as u16;
+        set_u16(&mut data, 2, len);
+        assert!(parse_ed25519_instruction(&data).is_none());
+
+        let mut data = ed25519_ix_data(&Pubkey::new_unique(), b"digest");
+        set_u16(&mut data, 6, len - 16);
+        assert!(parse_ed25519_instruction(&data).is_none());
+    }
+
+    #[test]
+    fn ed25519_rejects_truncated_data() {
+        let data = ed25519_ix_data(&Pubkey::new_unique(), b"digest");
+        assert!(parse_ed25519_instruction(&data[..10]).is_none());
+        assert!(parse_ed25519_instruction(&[]).is_none());
+    }
+
+    fn test_pool() -> Pool {
+        Pool {
+            authority: Pubkey::default(),
+            collateral_mint: Pubkey::default(),
+            borrow_mint: Pubkey::default(),
+            pool_id: 0,
+            share_mint: Pubkey::default(),
+            ltv_ratio: 7_500,
+            rate_model: InterestRateModel {
+                base_rate: 0,
+                slope_low: 400,
+                slope_high: 6_000,
+                optimal_utilization: 8_000,
+            },
+            liquidation_threshold: 8_000,
+            liquidation_config: LiquidationConfig {
+                close_factor: 5_000,
+                liquidation_bonus: 500,
+            },
+            insurance_factor: 0,
+            pending_insurance: 0,
+            reserve_factor: 0,
+            origination_fee: 0,
+            protocol_reserves: 0,
+            treasury: Pubkey::default(),
+            oracle: OracleConfig {
+                source: OracleSource::Mock,
+                price_feed: Pubkey::default(),
+                max_price_age_slots: 100,
+                max_confidence: 200,
+            },
+            collateral_decimals: 9,
+            borrow_decimals: 6,
+            total_collateral: 0,
+            total_deposits: 0,
+            total_borrows: 0,
+            borrow_index: INDEX_SCALE,
+            interest_remainder: 0,
+            last_accrual_ts: 0,
+            active_positions: 0,
+            attesters: [Pubkey::default(); MAX_ATTESTERS],
+            attester_count: 0,
+            pending_authority: Pubkey::default(),
+            guardian: Pubkey::default(),
+            risk_admin: Pubkey::default(),
+            attester_manager: Pubkey::default(),
+            pause_flags: 0,
+            bump: 0,
+        }
+    }
+
+    fn price(price: i64, expo: i32) -> OraclePrice {
+        OraclePrice {
+            price,
+            conf: 0,
+            expo,
+            publish_slot: 0,
+        }
+    }
+
+    fn pyth_account_data(status: u32, price: i64, conf: u64, expo: i32, slot: u64) -> Vec<u8> {
+        let mut data = vec![0u8; PYTH_PRICE_ACCOUNT_MIN_LEN];
+        data[0..4].copy_from_slice(&PYTH_MAGIC.to_le_bytes());
+        data[4..8].copy_from_slice(&PYTH_VERSION.to_le_bytes());
+        data[8..12].copy_from_slice(&PYTH_PRICE_ACCOUNT_TYPE.to_le_bytes());
+        data[PYTH_EXPO_OFFSET..PYTH_EXPO_OFFSET + 4].copy_from_slice(&expo.to_le_bytes());
+        data[PYTH_AGG_PRICE_OFFSET..PYTH_AGG_PRICE_OFFSET + 8].copy_from_slice(&price.to_le_bytes());
+        data[PYTH_AGG_CONF_OFFSET..PYTH_AGG_CONF_OFFSET + 8].copy_from_slice(&conf.to_le_bytes());
+        data[PYTH_AGG_STATUS_OFFSET..PYTH_AGG_STATUS_OFFSET + 4].copy_from_slice(&status.to_le_bytes());
+        data[PYTH_AGG_PUBLISH_SLOT_OFFSET..PYTH_AGG_PUBLISH_SLOT_OFFSET + 8]
+            .copy_from_slice(&slot.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn pyth_parses_trading_price() {
+        let data = pyth_account_data(PYTH_STATUS_TRADING, 15_000_000_000, 5_000_000, -8, 42);
+
+        let parsed = parse_pyth_price(&data).unwrap();
+        assert_eq!(parsed.price, 15_000_000_000);
+        assert_eq!(parsed.conf, 5_000_000);
+        assert_eq!(parsed.expo, -8);
+        assert_eq!(parsed.publish_slot, 42);
+    }
+
+    #[test]
+    fn pyth_rejects_non_trading_status() {
+        // unknown, halted, auction
+        for status in [0, 2, 3] {
+            let data = pyth_account_data(status, 15_000_000_000, 0, -8, 42);
+            assert!(parse_pyth_price(&data).is_none());
+        }
+    }
+
+    #[test]
+    fn pyth_rejects_foreign_accounts() {
+        let mut data = pyth_account_data(PYTH_STATUS_TRADING, 1, 0, 0, 0);
+        data[0] ^= 0xff;
+        assert!(parse_pyth_price(&data).is_none());
+
+        let data = pyth_account_data(PYTH_STATUS_TRADING, 1, 0, 0, 0);
+        assert!(parse_pyth_price(&data[..PYTH_PRICE_ACCOUNT_MIN_LEN - 1]).is_none());
+    }
+
+    #[test]
+    fn seize_amount_with_negative_exponent() {
+        // 150 USDC per collateral token, quoted as 150e8 * 10^-8
+        let pool = test_pool();
+        let seize = pool.liquidation_seize_amount(150_000_000, &price(15_000_000_000, -8));
+        // 150 USDC + 5% bonus buys 1.05 collateral tokens (9 decimals)
+        assert_eq!(seize, Some(1_050_000_000));
+    }
+
+    #[test]
+    fn seize_amount_with_positive_exponent() {
+        // 150 USDC per collateral token, quoted as 15 * 10^1
+        let pool = test_pool();
+        let seize = pool.liquidation_seize_amount(150_000_000, &price(15, 1));
+        assert_eq!(seize, Some(1_050_000_000));
+    }
+
+    #[test]
+    fn seize_amount_rounds_down_and_rejects_bad_prices() {
+        let pool = test_pool();
+        // 1 borrow unit at 3 USDC per token: 1.05e-6 / 3 tokens = 350 base units
+        assert_eq!(pool.liquidation_seize_amount(1, &price(3, 0)), Some(350));
+        // 1 borrow unit at 7 USDC per token: 150 base units, rounded down
+        assert_eq!(pool.liquidation_seize_amount(1, &price(7, 0)), Some(150));
+        assert_eq!(pool.liquidation_seize_amount(1, &price(0, 0)), None);
+        assert_eq!(pool.liquidation_seize_amount(1, &price(-1, 0)), None);
+    }
+
+    #[test]
+    fn collateral_value_rounds_up_in_borrow_units() {
+        let pool = test_pool();
+        // 1 collateral token at 150 USDC
+        let value = pool.collateral_value(1_000_000_000, &price(15_000_000_000, -8));
+        assert_eq!(value, Some(150_000_000));
+        assert_eq!(pool.collateral_value(1_000_000_000, &price(15, 1)), Some(150_000_000));
+        // 1 base unit is worth 1.5e-7 USDC, charged as one borrow unit
+        assert_eq!(pool.collateral_value(1, &price(150, 0)), Some(1));
+        assert_eq!(pool.collateral_value(0, &price(150, 0)), Some(0));
+        assert_eq!(pool.collateral_value(1, &price(-1, 0)), None);
+    }
+
+    #[test]
+    fn mock_feed_is_read_through_the_adapter() {
+        let feed = MockPriceFeed {
+            authority: Pubkey::new_unique(),
+            price: 15_000_000_000,
+            conf: 1_000,
+            expo: -8,
+            publish_slot: 9,
+        };
+        let mut data = Vec::new();
+        feed.try_serialize(&mut data).unwrap();
+
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let info = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &crate::ID, false, 0);
+
+        let loaded = OracleSource::Mock.load_price(&info).unwrap();
+        assert_eq!(loaded.price, 15_000_000_000);
+        assert_eq!(loaded.conf, 1_000);
+        assert_eq!(loaded.expo, -8);
+        assert_eq!(loaded.publish_slot, 9);
+    }
+
+    #[test]
+    fn mock_feed_must_be_owned_by_the_program() {
+        let feed = MockPriceFeed {
+            authority: Pubkey::new_unique(),
+            price: 1,
+            conf: 0,
+            expo: 0,
+            publish_slot: 0,
+        };
+        let mut data = Vec::new();
+        feed.try_serialize(&mut data).unwrap();
+
+        let key = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let mut lamports = 0;
+        let info = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+
+        assert!(OracleSource::Mock.load_price(&info).is_err());
+    }
+
+    #[test]
+    fn borrow_rate_follows_the_kink() {
+        let model = InterestRateModel {
+            base_rate: 200,
+            slope_low: 400,
+            slope_high: 6_000,
+            optimal_utilization: 8_000,
+        };
+        assert_eq!(model.borrow_rate(0), 200);
+        assert_eq!(model.borrow_rate(4_000), 400);
+        assert_eq!(model.borrow_rate(8_000), 600);
+        // Past the kink the steep slope applies to the excess only
+        assert_eq!(model.borrow_rate(9_000), 3_600);
+        assert_eq!(model.borrow_rate(10_000), 6_600);
+        // Utilization is clamped at 100%
+        assert_eq!(model.borrow_rate(12_000), 6_600);
+    }
+
+    #[test]
+    fn utilization_is_capped_and_handles_empty_pools() {
+        let mut pool = test_pool();
+        assert_eq!(pool.utilization(), 0);
+
+        pool.total_deposits = 1_000;
+        pool.total_borrows = 250;
+        assert_eq!(pool.utilization(), 2_500);
+
+        pool.total_borrows = 2_000;
+        assert_eq!(pool.utilization(), 10_000);
+    }
+
+    fn accruing_pool() -> Pool {
+        // 6-decimal pool, 100 tokens borrowed at 50% utilization (2.5% APR)
+        let mut pool = test_pool();
+        pool.total_deposits = 200_000_000;
+        pool.total_borrows = 100_000_000;
+        pool
+    }
+
+    #[test]
+    fn accrual_carries_interest_that_rounds_to_zero() {
+        let mut pool = accruing_pool();
+
+        // ~0.08 base units of interest per second
+        assert_eq!(pool.accrue_interest(1).unwrap(), 0);
+        assert_eq!(pool.total_borrows, 100_000_000);
+        assert!(pool.interest_remainder > 0);
+        assert!(pool.borrow_index > INDEX_SCALE);
+
+        let mut frequent = 0;
+        for now in 2..=1_000 {
+            frequent += pool.accrue_interest(now).unwrap();
+        }
+
+        let mut single = accruing_pool();
+        let once = single.accrue_interest(1_000).unwrap();
+
+        // Accruing every second credits the same interest as one accrual
+        // (up to compounding on the few units credited along the way)
+        assert!(once > 0);
+        assert!(frequent.abs_diff(once) <= 1, "{} vs {}", frequent, once);
+    }
+
+    #[test]
+    fn accrual_splits_interest_between_lenders_insurance_and_reserves() {
+        let mut pool = accruing_pool();
+        pool.insurance_factor = 1_000;
+        pool.reserve_factor = 2_000;
+
+        let interest = pool.accrue_interest(SECONDS_PER_YEAR as i64).unwrap();
+        // 2.5% of 100 tokens over a year
+        assert_eq!(interest, 2_500_000);
+        assert_eq!(pool.total_borrows, 102_500_000);
+        assert_eq!(pool.pending_insurance, 250_000);
+        assert_eq!(pool.protocol_reserves, 500_000);
+        assert_eq!(pool.total_deposits, 201_750_000);
+        assert_eq!(pool.borrow_index, INDEX_SCALE / 1_000 * 1_025);
+    }
+
+    #[test]
+    fn repaying_accrued_debt_clears_total_borrows() {
+        let mut pool = accruing_pool();
+        pool.insurance_factor = 1_000;
+        pool.reserve_factor = 1_000;
+        let (debt, debt_index) = (pool.total_borrows, pool.borrow_index);
+
+        for now in [1, 7, 3_600, 86_400, 1_000_003, SECONDS_PER_YEAR as i64] {
+            pool.accrue_interest(now).unwrap();
+        }
+
+        let owed = pool.accrued_debt(debt, debt_index).unwrap();
+        assert!(owed > debt);
+        assert!(owed >= pool.total_borrows && owed - pool.total_borrows <= 1);
+        assert_eq!(pool.total_borrows.saturating_sub(owed), 0);
+    }
+
+    #[test]
+    fn accrued_debts_of_all_positions_cover_total_borrows() {
+        let mut pool = accruing_pool();
+        let first = (pool.total_borrows, pool.borrow_index);
+
+        pool.accrue_interest(86_400).unwrap();
+        let second = (25_000_000, pool.borrow_index);
+        pool.total_borrows += second.0;
+
+        pool.accrue_interest(SECONDS_PER_YEAR as i64).unwrap();
+
+        let owed = pool.accrued_debt(first.0, first.1).unwrap()
+            + pool.accrued_debt(second.0, second.1).unwrap();
+        assert!(owed >= pool.total_borrows && owed - pool.total_borrows <= 2);
+    }
+
+    #[test]
+    fn first_supply_mints_shares_one_to_one() {
+        let pool = test_pool();
+        assert_eq!(pool.shares_for_amount(1_000_000, 0), Some(1_000_000));
+        assert_eq!(pool.amount_for_shares(1_000_000, 0), None);
+    }
+
+    #[test]
+    fn shares_appreciate_with_accrued_interest() {
+        let mut pool = accruing_pool();
+        let supply = pool.total_deposits;
+        assert_eq!(pool.shares_for_amount(1_000, supply), Some(1_000));
+
+        pool.accrue_interest(SECONDS_PER_YEAR as i64).unwrap();
+        // Deposits grew 2.5m on 200m: a later supplier gets fewer shares and
+        // existing shares redeem for more
+        assert_eq!(pool.total_deposits, 202_500_000);
+        assert_eq!(pool.shares_for_amount(1_012_500, supply), Some(1_000_000));
+        assert_eq!(pool.amount_for_shares(1_000_000, supply), Some(1_012_500));
+    }
+
+    #[test]
+    fn share_conversions_round_in_favour_of_the_pool() {
+        let mut pool = test_pool();
+        pool.total_deposits = 1_000;
+        let supply = 3;
+
+        // 1 token buys 0.003 shares and a share is worth 333.33 tokens
+        assert_eq!(pool.shares_for_amount(1, supply), Some(0));
+        assert_eq!(pool.shares_for_amount(334, supply), Some(1));
+        assert_eq!(pool.amount_for_shares(1, supply), Some(333));
+        assert_eq!(pool.amount_for_shares(supply, supply), Some(1_000));
+    }
+
+    #[test]
+    fn unbacked_shares_do_not_absorb_new_supply() {
+        let mut pool = test_pool();
+        pool.total_deposits = 0;
+        assert_eq!(pool.shares_for_amount(1_000_000, 500), None);
+        assert_eq!(pool.amount_for_shares(500, 500), Some(0));
+    }
+
+    #[test]
+    fn reserves_and_insurance_are_backed_once_debt_is_repaid() {
+        let mut pool = accruing_pool();
+        pool.insurance_factor = 1_000;
+        pool.reserve_factor = 2_000;
+        // Vault cash: 200 supplied minus 100 lent out
+        let mut cash = pool.total_deposits - pool.total_borrows;
+        let (debt, debt_index) = (pool.total_borrows, pool.borrow_index);
+
+        pool.accrue_interest(SECONDS_PER_YEAR as i64).unwrap();
+        assert_eq!(pool.protocol_reserves, 500_000);
+        assert_eq!(pool.pending_insurance, 250_000);
+
+        // The borrower repays the attested accrued debt in full
+        let owed = pool.accrued_debt(debt, debt_index).unwrap();
+        cash += owed;
+        pool.total_borrows = pool.total_borrows.saturating_sub(owed);
+        assert_eq!(pool.total_borrows, 0);
+
+        // Every claim on the vault (lenders, insurance, reserves) is in cash
+        assert_eq!(pool.available_liquidity(), cash);
+        assert_eq!(
+            cash,
+            pool.total_deposits + pool.pending_insurance + pool.protocol_reserves
+        );
+    }
+
+    #[test]
+    fn accrual_ignores_non_advancing_clock() {
+        let mut pool = accruing_pool();
+        pool.last_accrual_ts = 100;
+
+        assert_eq!(pool.accrue_interest(100).unwrap(), 0);
+        assert_eq!(pool.accrue_interest(50).unwrap(), 0);
+        assert_eq!(pool.last_accrual_ts, 100);
+        assert_eq!(pool.borrow_index, INDEX_SCALE);
+    }
+
+    fn test_position() -> Position {
+        Position {
+            owner: Pubkey::new_unique(),
+            pool: Pubkey::default(),
+            index: 0,
+            encrypted_collateral: [1; 32],
+            encrypted_debt: [2; 32],
+            debt_index: INDEX_SCALE,
+            nonce: 3,
+            last_update: 0,
+            is_active: true,
+            bump: 0,
+        }
+    }
+
+    fn borrow_header(pool: &Pool) -> ProofHeader {
+        ProofHeader {
+            version: PROOF_VERSION,
+            kind: ProofKind::Borrow as u8,
+            nonce: 3,
+            expiry_slot: 100,
+            amount: 500,
+            param: pool.ltv_ratio,
+        }
+    }
+
+    #[test]
+    fn proof_header_must_match_the_transition() {
+        let pool = test_pool();
+        let header = borrow_header(&pool);
+        let transition = Transition {
+            kind: ProofKind::Borrow,
+            amount: 500,
+            param: pool.ltv_ratio,
+            new_collateral: &[1; 32],
+            new_debt: &[3; 32],
+            price: Some(price(150, 0)),
+            terms: Vec::new(),
+        };
+        assert!(header.authorizes(&transition, 3, 100));
+
+        let wrong_version = ProofHeader { version: PROOF_VERSION - 1, ..header };
+        assert!(!wrong_version.authorizes(&transition, 3, 100));
+        let wrong_kind = ProofHeader { kind: ProofKind::Withdraw as u8, ..header };
+        assert!(!wrong_kind.authorizes(&transition, 3, 100));
+        // Stale nonce, expired slot
+        assert!(!header.authorizes(&transition, 4, 100));
+        assert!(!header.authorizes(&transition, 3, 101));
+        let wrong_amount = ProofHeader { amount: 501, ..header };
+        assert!(!wrong_amount.authorizes(&transition, 3, 100));
+        // Attested against the liquidation threshold instead of the LTV
+        let wrong_param = ProofHeader { param: pool.liquidation_threshold, ..header };
+        assert!(!wrong_param.authorizes(&transition, 3, 100));
+    }
+
+    #[test]
+    fn liquidation_proof_must_use_the_threshold() {
+        let pool = test_pool();
+        let header = ProofHeader {
+            kind: ProofKind::Liquidate as u8,
+            ..borrow_header(&pool)
+        };
+        let transition = Transition {
+            kind: ProofKind::Liquidate,
+            amount: 500,
+            param: pool.liquidation_threshold,
+            new_collateral: &[0; 32],
+            new_debt: &[0; 32],
+            price: Some(price(150, 0)),
+            terms: Vec::new(),
+        };
+        assert!(!header.authorizes(&transition, 3, 100));
+
+        let header = ProofHeader { param: pool.liquidation_threshold, ..header };
+        assert!(header.authorizes(&transition, 3, 100));
+    }
+
+    #[test]
+    fn proof_digest_commits_to_every_input() {
+        let pool = test_pool();
+        let header = borrow_header(&pool);
+        let pool_key = Pubkey::new_unique();
+        let position_key = Pubkey::new_unique();
+        let position = test_position();
+        let transition = Transition {
+            kind: ProofKind::Borrow,
+            amount: 500,
+            param: pool.ltv_ratio,
+            new_collateral: &[1; 32],
+            new_debt: &[3; 32],
+            price: Some(price(150, 0)),
+            terms: vec![1, 2, 3],
+        };
+        let digest = |key: &Pubkey, pool: &Pool, position: &Position, transition: &Transition| {
+            compute_proof_digest(&header, key, pool, &position_key, position, transition)
+        };
+        let base = digest(&pool_key, &pool, &position, &transition);
+        assert_eq!(base, digest(&pool_key, &pool, &position, &transition));
+
+        // Pool and borrow index
+        assert_ne!(base, digest(&Pubkey::new_unique(), &pool, &position, &transition));
+        let accrued = Pool { borrow_index: INDEX_SCALE + 1, ..test_pool() };
+        assert_ne!(base, digest(&pool_key, &accrued, &position, &transition));
+
+        // Position owner, debt index and old ciphertexts
+        let changed = [
+            Position { owner: Pubkey::new_unique(), ..position.clone() },
+            Position { debt_index: INDEX_SCALE + 1, ..position.clone() },
+            Position { encrypted_collateral: [9; 32], ..position.clone() },
+            Position { encrypted_debt: [9; 32], ..position.clone() },
+        ];
+        for other in &changed {
+            assert_ne!(base, digest(&pool_key, &pool, other, &transition));
+        }
+        assert_ne!(
+            base,
+            compute_proof_digest(
+                &header,
+                &pool_key,
+                &pool,
+                &Pubkey::new_unique(),
+                &position,
+                &transition
+            )
+        );
+
+        // New ciphertexts, price and terms
+        let changed = [
+            Transition { new_collateral: &[9; 32], ..transition.clone() },
+            Transition { new_debt: &[9; 32], ..transition.clone() },
+            Transition { price: Some(price(151, 0)), ..transition.clone() },
+            Transition { price: None, ..transition.clone() },
+            Transition { terms: vec![1, 2, 4], ..transition.clone() },
+        ];
+        for other in &changed {
+            assert_ne!(base, digest(&pool_key, &pool, &position, other));
+        }
+    }
+
+    fn rejects(config: PoolConfig, error: ShadowLendError) -> bool {
+        matches!(
+            config.validate(),
+            Err(Error::AnchorError(e)) if e.error_code_number == u32::from(error)
+        )
+    }
+
+    #[test]
+    fn config_validation_bounds_risk_parameters() {
+        let valid = test_pool().config();
+        assert!(valid.validate().is_ok());
+
+        // 0 < ltv < threshold <= 100%
+        assert!(rejects(PoolConfig { ltv_ratio: 0, ..valid }, ShadowLendError::InvalidLtvRatio));
+        assert!(rejects(PoolConfig { ltv_ratio: 8_000, ..valid }, ShadowLendError::InvalidLtvRatio));
+        assert!(rejects(PoolConfig { ltv_ratio: 9_000, ..valid }, ShadowLendError::InvalidLtvRatio));
+        assert!(rejects(
+            PoolConfig { liquidation_threshold: 10_001, ..valid },
+            ShadowLendError::InvalidLiquidationThreshold
+        ));
+
+        // threshold * (1 + bonus) must stay below 100%
+        let tight = PoolConfig { liquidation_threshold: 9_500, ..valid };
+        assert!(PoolConfig {
+            liquidation_config: LiquidationConfig { close_factor: 5_000, liquidation_bonus: 500 },
+            ..tight
+        }
+        .validate()
+        .is_ok());
+        assert!(rejects(
+            PoolConfig {
+                liquidation_config: LiquidationConfig { close_factor: 5_000, liquidation_bonus: 600 },
+                ..tight
+            },
+            ShadowLendError::InvalidLiquidationConfig
+        ));
+
+        // Close factor in (0, 100%], bonus capped
+        let low = PoolConfig { ltv_ratio: 4_000, liquidation_threshold: 5_000, ..valid };
+        let out_of_bounds = [(0, 500), (10_001, 500), (5_000, MAX_LIQUIDATION_BONUS + 1)];
+        for (close_factor, liquidation_bonus) in out_of_bounds {
+            let liquidation_config = LiquidationConfig { close_factor, liquidation_bonus };
+            assert!(rejects(
+                PoolConfig { liquidation_config, ..low },
+                ShadowLendError::InvalidLiquidationConfig
+            ));
+        }
+        let liquidation_config = LiquidationConfig {
+            close_factor: 10_000,
+            liquidation_bonus: MAX_LIQUIDATION_BONUS,
+        };
+        assert!(PoolConfig { liquidation_config, ..low }.validate().is_ok());
+    }
+
+    #[test]
+    fn config_validation_bounds_rate_model() {
+        let valid = test_pool().config();
+        let model = valid.rate_model;
+
+        for rate_model in [
+            InterestRateModel { slope_low: 0, ..model },
+            InterestRateModel { slope_high: 0, ..model },
+            InterestRateModel { optimal_utilization: 0, ..model },
+            InterestRateModel { optimal_utilization: 10_000, ..model },
+            // Full utilization would pay base + slope_low + slope_high
+            InterestRateModel { slope_low: 40_001, slope_high: 60_000, ..model },
+        ] {
+            assert!(rejects(
+                PoolConfig { rate_model, ..valid },
+                ShadowLendError::InvalidInterestRateModel
+            ));
+        }
+
+        let rate_model = InterestRateModel { slope_low: 40_000, slope_high: 60_000, ..model };
+        assert_eq!(rate_model.borrow_rate(10_000), MAX_BORROW_RATE);
+        assert!(PoolConfig { rate_model, ..valid }.validate().is_ok());
+    }
+
+    #[test]
+    fn config_validation_caps_factors_and_fees() {
+        let valid = test_pool().config();
+
+        assert!(rejects(
+            PoolConfig { insurance_factor: MAX_INSURANCE_FACTOR + 1, ..valid },
+            ShadowLendError::InvalidInsuranceFactor
+        ));
+        assert!(rejects(
+            PoolConfig { reserve_factor: MAX_RESERVE_FACTOR + 1, ..valid },
+            ShadowLendError::InvalidReserveFactor
+        ));
+        assert!(rejects(
+            PoolConfig { origination_fee: MAX_ORIGINATION_FEE + 1, ..valid },
+            ShadowLendError::InvalidOriginationFee
+        ));
+        let capped = PoolConfig {
+            insurance_factor: MAX_INSURANCE_FACTOR,
+            reserve_factor: MAX_RESERVE_FACTOR,
+            origination_fee: MAX_ORIGINATION_FEE,
+            ..valid
+        };
+        assert!(capped.validate().is_ok());
+    }
+
+    #[test]
+    fn config_validation_checks_oracle() {
+        let valid = test_pool().config();
+        let oracle = valid.oracle;
+
+        for oracle in [
+            OracleConfig { max_price_age_slots: 0, ..oracle },
+            OracleConfig { max_confidence: 0, ..oracle },
+            OracleConfig { max_confidence: 10_001, ..oracle },
+        ] {
+            assert!(rejects(PoolConfig { oracle, ..valid }, ShadowLendError::InvalidOracleConfig));
+        }
+
+        // The mock source is only accepted by builds with the mock oracle
+        let pyth = PoolConfig {
+            oracle: OracleConfig { source: OracleSource::Pyth, ..oracle },
+            ..valid
+        };
+        assert!(valid.validate_for_build(true).is_ok());
+        assert!(pyth.validate_for_build(false).is_ok());
+        assert!(matches!(
+            valid.validate_for_build(false),
+            Err(Error::AnchorError(e))
+                if e.error_code_number == u32::from(ShadowLendError::InvalidOracleConfig)
+        ));
+    }
+
+    #[test]
+    fn risky_config_changes_require_the_timelock() {
+        let current = test_pool().config();
+        assert!(!current.requires_timelock(&current));
+
+        // Changes that cannot hurt existing borrowers apply immediately
+        let safer = [
+            PoolConfig { ltv_ratio: 7_900, ..current },
+            PoolConfig { liquidation_threshold: 8_500, ..current },
+            PoolConfig {
+                oracle: OracleConfig {
+                    max_price_age_slots: 50,
+                    max_confidence: 100,
+                    ..current.oracle
+                },
+                ..current
+            },
+        ];
+        for config in &safer {
+            assert!(!config.requires_timelock(&current));
+        }
+
+        let liquidation = current.liquidation_config;
+        let oracle = current.oracle;
+        let riskier = [
+            PoolConfig { ltv_ratio: 7_000, liquidation_threshold: 7_900, ..current },
+            PoolConfig { ltv_ratio: 7_400, ..current },
+            PoolConfig {
+                liquidation_config: LiquidationConfig { close_factor: 5_001, ..liquidation },
+                ..current
+            },
+            PoolConfig {
+                liquidation_config: LiquidationConfig { liquidation_bonus: 501, ..liquidation },
+                ..current
+            },
+            PoolConfig { oracle: OracleConfig { source: OracleSource::Pyth, ..oracle }, ..current },
+            PoolConfig {
+                oracle: OracleConfig { price_feed: Pubkey::new_unique(), ..oracle },
+                ..current
+            },
+            PoolConfig { oracle: OracleConfig { max_price_age_slots: 101, ..oracle }, ..current },
+            PoolConfig { oracle: OracleConfig { max_confidence: 201, ..oracle }, ..current },
+        ];
+        for config in &riskier {
+            assert!(config.requires_timelock(&current));
+        }
+    }
+
+    #[test]
+    fn queued_change_waits_for_eta_and_an_unchanged_pool() {
+        let base = test_pool().config();
+        let change = PendingConfigChange {
+            pool: Pubkey::new_unique(),
+            base_config: base,
+            config: PoolConfig { ltv_ratio: 7_000, ..base },
+            queued_at: 0,
+            eta: CONFIG_CHANGE_DELAY,
+            bump: 0,
+        };
+        let code = |result: Result<()>| match result {
+            Err(Error::AnchorError(e)) => Some(e.error_code_number),
+            _ => None,
+        };
+
+        assert!(change.check_executable(&base, CONFIG_CHANGE_DELAY).is_ok());
+        assert_eq!(
+            code(change.check_executable(&base, CONFIG_CHANGE_DELAY - 1)),
+            Some(u32::from(ShadowLendError::TimelockNotElapsed))
+        );
+
+        // A direct update landed while the change was queued
+        let updated = PoolConfig { origination_fee: 10, ..base };
+        assert_eq!(
+            code(change.check_executable(&updated, CONFIG_CHANGE_DELAY)),
+            Some(u32::from(ShadowLendError::ConfigChangedSinceQueued))
+        );
+    }
 }